@@ -0,0 +1,349 @@
+use std::{fmt, ops};
+
+use crate::{Color, Position};
+
+/// A set of tiles stored as one bit per tile.
+///
+/// Bit `rank * 8 + file` represents `Position::new(file, rank)`, so bit 0 is
+/// a8 and bit 63 is h1, matching the layout of `Board::tiles`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Self = Self(0);
+    pub const FULL: Self = Self(!0);
+    /// All dark tiles, such as a1 and h8
+    pub const DARK: Self = Self(0x55aa_55aa_55aa_55aa);
+    /// All light tiles, such as h1 and a8
+    pub const LIGHT: Self = Self(!Self::DARK.0);
+
+    pub fn from_position(pos: Position) -> Self {
+        Self(1 << pos.index())
+    }
+    pub fn contains(self, pos: Position) -> bool {
+        self.0 & (1 << pos.index()) != 0
+    }
+    pub fn insert(&mut self, pos: Position) {
+        self.0 |= 1 << pos.index();
+    }
+    pub fn remove(&mut self, pos: Position) {
+        self.0 &= !(1 << pos.index());
+    }
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+    /// Returns the tile with the lowest index, i.e. the one closest to a8.
+    pub fn first(self) -> Option<Position> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(Position::from_index(self.0.trailing_zeros() as u8))
+        }
+    }
+    /// Returns the tile with the highest index, i.e. the one closest to h1.
+    pub fn last(self) -> Option<Position> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(Position::from_index(63 - self.0.leading_zeros() as u8))
+        }
+    }
+    /// All tiles on `rank`, where 0 is the eighth rank as in `Position`.
+    pub fn rank(rank: u8) -> Self {
+        Self(0xff << (rank * 8))
+    }
+    /// All tiles on `file`, where 0 is the a-file.
+    pub fn file(file: u8) -> Self {
+        Self(0x0101_0101_0101_0101 << file)
+    }
+}
+
+pub struct Iter(u64);
+
+impl Iterator for Iter {
+    type Item = Position;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros();
+        self.0 &= self.0 - 1;
+        Some(Position::from_index(index as u8))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.0.count_ones() as usize;
+        (n, Some(n))
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = Position;
+    type IntoIter = Iter;
+    fn into_iter(self) -> Self::IntoIter {
+        Iter(self.0)
+    }
+}
+
+impl From<Position> for Bitboard {
+    fn from(pos: Position) -> Self {
+        Self::from_position(pos)
+    }
+}
+
+impl ops::BitAnd for Bitboard {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl ops::BitOr for Bitboard {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitXor for Bitboard {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl ops::Not for Bitboard {
+    type Output = Self;
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl ops::BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl ops::BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in 0..8 {
+            write!(f, "{}", 8 - rank)?;
+            for file in 0..8 {
+                let c = if self.contains(Position::new_unchecked(file, rank)) {
+                    'x'
+                } else {
+                    '.'
+                };
+                write!(f, " {}", c)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "  A B C D E F G H")
+    }
+}
+
+const KNIGHT_DELTAS: [(i8, i8); 8] = [
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+    (1, 2),
+    (2, 1),
+];
+
+const KING_DELTAS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Sliding directions as (file, rank) deltas. The first four are orthogonal
+/// and the last four diagonal.
+const DIRECTIONS: [(i8, i8); 8] = [
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+];
+
+const fn on_board(file: i8, rank: i8) -> bool {
+    file >= 0 && file < 8 && rank >= 0 && rank < 8
+}
+
+const fn leaper_table(deltas: &[(i8, i8); 8]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let mut i = 0;
+        while i < 8 {
+            let file = (sq % 8) as i8 + deltas[i].0;
+            let rank = (sq / 8) as i8 + deltas[i].1;
+            if on_board(file, rank) {
+                table[sq] |= 1 << (rank * 8 + file);
+            }
+            i += 1;
+        }
+        sq += 1;
+    }
+    table
+}
+
+const fn pawn_table() -> [[u64; 64]; 2] {
+    let mut table = [[0; 64]; 2];
+    let mut sq = 0;
+    while sq < 64 {
+        let file = (sq % 8) as i8;
+        let rank = (sq / 8) as i8;
+        let mut df = -1;
+        while df <= 1 {
+            // White moves towards rank 0, black towards rank 7
+            if on_board(file + df, rank - 1) {
+                table[0][sq] |= 1 << ((rank - 1) * 8 + file + df);
+            }
+            if on_board(file + df, rank + 1) {
+                table[1][sq] |= 1 << ((rank + 1) * 8 + file + df);
+            }
+            df += 2;
+        }
+        sq += 1;
+    }
+    table
+}
+
+const fn ray_table() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut dir = 0;
+    while dir < 8 {
+        let mut sq = 0;
+        while sq < 64 {
+            let mut file = (sq % 8) as i8 + DIRECTIONS[dir].0;
+            let mut rank = (sq / 8) as i8 + DIRECTIONS[dir].1;
+            while on_board(file, rank) {
+                table[dir][sq] |= 1 << (rank * 8 + file);
+                file += DIRECTIONS[dir].0;
+                rank += DIRECTIONS[dir].1;
+            }
+            sq += 1;
+        }
+        dir += 1;
+    }
+    table
+}
+
+/// Returns `(between, line)` tables. `between[a][b]` holds the tiles strictly
+/// between `a` and `b` and `line[a][b]` the whole line through both, if they
+/// share a rank, file or diagonal.
+const fn line_tables() -> ([[u64; 64]; 64], [[u64; 64]; 64]) {
+    let mut between = [[0; 64]; 64];
+    let mut line = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut dir = 0;
+        while dir < 8 {
+            let (df, dr) = DIRECTIONS[dir];
+            let mut path = 0;
+            let mut file = (a % 8) as i8 + df;
+            let mut rank = (a / 8) as i8 + dr;
+            while on_board(file, rank) {
+                let b = (rank * 8 + file) as usize;
+                between[a][b] = path;
+                line[a][b] = RAYS[dir][a] | RAYS[(dir + 2) % 4 + dir / 4 * 4][a] | 1 << a;
+                path |= 1 << b;
+                file += df;
+                rank += dr;
+            }
+            dir += 1;
+        }
+        a += 1;
+    }
+    (between, line)
+}
+
+static KNIGHT_ATTACKS: [u64; 64] = leaper_table(&KNIGHT_DELTAS);
+static KING_ATTACKS: [u64; 64] = leaper_table(&KING_DELTAS);
+static PAWN_ATTACKS: [[u64; 64]; 2] = pawn_table();
+const RAYS: [[u64; 64]; 8] = ray_table();
+static RAY_TABLE: [[u64; 64]; 8] = RAYS;
+static LINES: ([[u64; 64]; 64], [[u64; 64]; 64]) = line_tables();
+
+fn ray_attacks(dir: usize, sq: usize, occupied: u64) -> u64 {
+    let ray = RAY_TABLE[dir][sq];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let (df, dr) = DIRECTIONS[dir];
+    // Rays pointing towards higher indices are cut at the lowest blocker and
+    // vice versa
+    let blocker = if dr > 0 || dr == 0 && df > 0 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAY_TABLE[dir][blocker as usize]
+}
+
+pub fn knight_attacks(from: Position) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[from.index() as usize])
+}
+
+pub fn king_attacks(from: Position) -> Bitboard {
+    Bitboard(KING_ATTACKS[from.index() as usize])
+}
+
+/// Tiles a pawn of `color` at `from` attacks diagonally.
+pub fn pawn_attacks(from: Position, color: Color) -> Bitboard {
+    Bitboard(PAWN_ATTACKS[color as usize][from.index() as usize])
+}
+
+pub fn rook_attacks(from: Position, occupied: Bitboard) -> Bitboard {
+    let sq = from.index() as usize;
+    Bitboard((0..4).fold(0, |acc, dir| acc | ray_attacks(dir, sq, occupied.0)))
+}
+
+pub fn bishop_attacks(from: Position, occupied: Bitboard) -> Bitboard {
+    let sq = from.index() as usize;
+    Bitboard((4..8).fold(0, |acc, dir| acc | ray_attacks(dir, sq, occupied.0)))
+}
+
+pub fn queen_attacks(from: Position, occupied: Bitboard) -> Bitboard {
+    rook_attacks(from, occupied) | bishop_attacks(from, occupied)
+}
+
+/// Tiles strictly between `a` and `b`. Empty if they are not on a common rank,
+/// file or diagonal.
+pub fn between(a: Position, b: Position) -> Bitboard {
+    Bitboard(LINES.0[a.index() as usize][b.index() as usize])
+}
+
+/// The full rank, file or diagonal going through both `a` and `b`. Empty if
+/// there is no such line.
+pub fn line(a: Position, b: Position) -> Bitboard {
+    Bitboard(LINES.1[a.index() as usize][b.index() as usize])
+}
@@ -1,30 +1,260 @@
-use std::{fmt, ops};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops,
+};
 
-use crate::{piece, Color, Piece, Position};
+use crate::{bitboard, piece, Bitboard, Color, Move, Piece, Position};
 
+mod attacks;
 mod fen;
+mod legality;
+mod make_move;
+mod see;
+mod zobrist;
+
+pub use attacks::{AttackMap, Pin};
+pub use make_move::Undo;
 
 /// Represents the state of a chess board.
 ///
+/// The pieces are stored both as one `Bitboard` per kind and color, which is
+/// what move generation works on, and as a grid of tiles for cheap lookups.
+/// The two are always kept in sync.
+///
 /// Note: the `Board` must always represent a valid state. Some methods might
 /// panic if the is not the case.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     pub(crate) tiles: [[Option<Piece>; 8]; 8],
+    pub(crate) kinds: [Bitboard; 6],
+    pub(crate) colors: [Bitboard; 2],
     pub(crate) next_to_move: Color,
-    pub(crate) can_castle_white_kingside: bool,
-    pub(crate) can_castle_white_queenside: bool,
-    pub(crate) can_castle_black_kingside: bool,
-    pub(crate) can_castle_black_queenside: bool,
+    /// Files of the rooks that can still castle, in the order white
+    /// kingside, white queenside, black kingside, black queenside
+    pub(crate) castling: [Option<u8>; 4],
+    /// Whether the game is Chess960, which only affects how castling moves
+    /// are written
+    pub(crate) chess960: bool,
     pub(crate) en_passant_square: Option<Position>,
     pub(crate) halfmove_counter: u16,
     pub(crate) move_number: u16,
+    /// Zobrist hash of everything but the en passant square
+    pub(crate) hash: u64,
 }
 
 impl Board {
+    /// A board without any pieces on it, white to move and no castling rights.
+    pub(crate) fn empty() -> Self {
+        Self {
+            tiles: [[None; 8]; 8],
+            kinds: [Bitboard::EMPTY; 6],
+            colors: [Bitboard::EMPTY; 2],
+            next_to_move: Color::White,
+            castling: [None; 4],
+            chess960: false,
+            en_passant_square: None,
+            halfmove_counter: 0,
+            move_number: 1,
+            hash: 0,
+        }
+    }
+    /// Returns the Chess960 starting position with number `index`, from 0 to
+    /// 959, in the standard numbering where 518 is the usual starting
+    /// position. Returns `None` for larger numbers.
+    pub fn chess960(index: u16) -> Option<Self> {
+        if index >= 960 {
+            return None;
+        }
+        /// Places `name` on the `n`th tile of `rank` that is still empty.
+        fn place(rank: &mut [Option<char>; 8], n: usize, name: char) {
+            *rank.iter_mut().filter(|t| t.is_none()).nth(n).unwrap() = Some(name);
+        }
+        const KNIGHTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+        let mut rank = [None; 8];
+        let mut n = index as usize;
+        // One bishop on a light tile and one on a dark
+        rank[n % 4 * 2 + 1] = Some('B');
+        n /= 4;
+        rank[n % 4 * 2] = Some('B');
+        n /= 4;
+        place(&mut rank, n % 6, 'Q');
+        n /= 6;
+        let (first, second) = KNIGHTS[n];
+        // The second first, so that the first does not shift it
+        place(&mut rank, second, 'N');
+        place(&mut rank, first, 'N');
+        // The king always ends up between the rooks
+        for name in ['R', 'K', 'R'] {
+            place(&mut rank, 0, name);
+        }
+
+        let white = rank.iter().map(|t| t.unwrap()).collect::<String>();
+        let rooks = (0..8u8).filter(|&f| rank[f as usize] == Some('R'));
+        let castling = rooks.rev().map(|f| (b'A' + f) as char).collect::<String>();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+            white.to_ascii_lowercase(),
+            white,
+            castling,
+            castling.to_ascii_lowercase()
+        );
+        // Position 518 is set up like standard chess, but is still Chess960
+        let mut board = Self::from_fen(&fen).ok()?;
+        board.chess960 = true;
+        Some(board)
+    }
     pub fn tiles(&self) -> &[[Option<Piece>; 8]; 8] {
         &self.tiles
     }
+    /// Places `piece` at `pos`, returning whatever was there before.
+    ///
+    /// Note: nothing else about the board is updated, so it's up to the caller
+    /// to make sure that the board is in a valid state afterwards.
+    pub fn set_tile(&mut self, pos: Position, piece: Option<Piece>) -> Option<Piece> {
+        let old = self.remove_piece(pos);
+        if let Some(piece) = piece {
+            self.put_piece(pos, piece);
+        }
+        old
+    }
+    pub(crate) fn put_piece(&mut self, pos: Position, piece: Piece) {
+        debug_assert!(self[pos].is_none());
+        self.tiles[pos.rank() as usize][pos.file() as usize] = Some(piece);
+        self.kinds[piece.kind as usize].insert(pos);
+        self.colors[piece.color as usize].insert(pos);
+        self.hash ^= zobrist::piece_key(piece, pos);
+    }
+    pub(crate) fn remove_piece(&mut self, pos: Position) -> Option<Piece> {
+        let piece = self.tiles[pos.rank() as usize][pos.file() as usize].take()?;
+        self.kinds[piece.kind as usize].remove(pos);
+        self.colors[piece.color as usize].remove(pos);
+        self.hash ^= zobrist::piece_key(piece, pos);
+        Some(piece)
+    }
+    /// All tiles that have a piece on them.
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+    /// All tiles with a piece of color `color` on them.
+    pub fn by_color(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
+    /// All tiles with a piece of kind `kind` on them, regardless of color.
+    pub fn by_kind(&self, kind: piece::Kind) -> Bitboard {
+        self.kinds[kind as usize]
+    }
+    /// All tiles containing exactly `piece`.
+    pub fn by_piece(&self, piece: Piece) -> Bitboard {
+        self.by_kind(piece.kind) & self.by_color(piece.color)
+    }
+    /// All pieces, of both colors, attacking `pos` if the tiles in `occupied`
+    /// are the ones with something on them.
+    pub(crate) fn attackers(&self, pos: Position, occupied: Bitboard) -> Bitboard {
+        use piece::Kind::*;
+        let diagonal = self.by_kind(Bishop) | self.by_kind(Queen);
+        let orthogonal = self.by_kind(Rook) | self.by_kind(Queen);
+        bitboard::knight_attacks(pos) & self.by_kind(Knight)
+            | bitboard::king_attacks(pos) & self.by_kind(King)
+            | bitboard::bishop_attacks(pos, occupied) & diagonal
+            | bitboard::rook_attacks(pos, occupied) & orthogonal
+            | bitboard::pawn_attacks(pos, Color::White)
+                & self.by_piece(Piece::new(Color::Black, Pawn))
+            | bitboard::pawn_attacks(pos, Color::Black)
+                & self.by_piece(Piece::new(Color::White, Pawn))
+    }
+    /// Returns all legal moves for the player next to move. Moves promoting a
+    /// pawn are only returned once, with `promotion` set to `None`.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.by_color(self.next_to_move)
+            .into_iter()
+            .flat_map(move |from| {
+                self[from].unwrap().moves(self, from).map(move |to| Move {
+                    from,
+                    to,
+                    promotion: None,
+                })
+            })
+    }
+    /// Same as `legal_moves`, but with each move promoting a pawn returned
+    /// once per kind it can be promoted to, queen first.
+    pub(crate) fn legal_moves_with_promotions(&self) -> impl Iterator<Item = Move> + '_ {
+        self.legal_moves().flat_map(move |m| {
+            let piece = self[m.from].unwrap();
+            let promotes =
+                piece.kind == piece::Kind::Pawn && m.to.rank() == piece.color.other().home_rank();
+            let kinds: &[Option<piece::Kind>] = if promotes {
+                &[
+                    Some(piece::Kind::Queen),
+                    Some(piece::Kind::Knight),
+                    Some(piece::Kind::Rook),
+                    Some(piece::Kind::Bishop),
+                ]
+            } else {
+                &[None]
+            };
+            kinds.iter().map(move |&promotion| Move { promotion, ..m })
+        })
+    }
+    /// Indicates if the player next to move has any legal moves.
+    pub(crate) fn has_legal_moves(&self) -> bool {
+        self.legal_moves().next().is_some()
+    }
+    /// Returns the en passant square only if a pawn of the player next to move
+    /// stands next to the pawn that just moved two steps. Whether the capture
+    /// would leave the king in check is not considered.
+    pub(crate) fn capturable_en_passant_square(&self) -> Option<Position> {
+        let ep = self.en_passant_square?;
+        let pawns = self.by_piece(Piece::new(self.next_to_move, piece::Kind::Pawn));
+        if (bitboard::pawn_attacks(ep, self.next_to_move.other()) & pawns).is_empty() {
+            None
+        } else {
+            Some(ep)
+        }
+    }
+    /// Indicates if `color` lacks the material to ever checkmate the other
+    /// player, no matter how the other player moves. This is the case with
+    /// only a king, with a king and a single knight against a king that has
+    /// nothing but queens to block with, and with bishops that all stand on
+    /// tiles of the same color while there are no knights or pawns on the
+    /// board.
+    pub fn has_insufficient_material(&self, color: Color) -> bool {
+        use piece::Kind::*;
+        let own = self.by_color(color);
+        let heavy = self.by_kind(Pawn) | self.by_kind(Rook) | self.by_kind(Queen);
+        if !(own & heavy).is_empty() {
+            return false;
+        }
+        if !(own & self.by_kind(Knight)).is_empty() {
+            let blockers =
+                self.by_color(color.other()) & !self.by_kind(King) & !self.by_kind(Queen);
+            return own.count() <= 2 && blockers.is_empty();
+        }
+        let bishops = self.by_kind(Bishop);
+        if !(own & bishops).is_empty() {
+            // Bishops on one color can only mate with something blocking on
+            // the other color, which only pawns and knights can do
+            let same_color =
+                (bishops & Bitboard::DARK).is_empty() || (bishops & Bitboard::LIGHT).is_empty();
+            return same_color && (self.by_kind(Pawn) | self.by_kind(Knight)).is_empty();
+        }
+        true
+    }
+    /// Indicates if neither player can ever checkmate the other.
+    pub(crate) fn is_dead_position(&self) -> bool {
+        self.has_insufficient_material(Color::White) && self.has_insufficient_material(Color::Black)
+    }
     /// Signifies wich color in next up to make a move. Starts as `Color::White`
     /// on a `Default` board
     pub fn next_to_move(&self) -> Color {
@@ -39,6 +269,7 @@ impl Board {
             self.move_number += 1;
         }
         self.next_to_move = self.next_to_move.other();
+        self.hash ^= zobrist::black_to_move_key();
     }
     /// Retrieves the tile where a pawn can move to capture another pawn that
     /// just moved two ranks
@@ -49,53 +280,125 @@ impl Board {
         self.en_passant_square = eps;
     }
     pub fn can_castle_kingside(&self, color: Color) -> bool {
-        match color {
-            Color::White => self.can_castle_white_kingside,
-            Color::Black => self.can_castle_black_kingside,
-        }
+        self.castling_rook(color, true).is_some()
     }
     pub fn can_castle_queenside(&self, color: Color) -> bool {
-        match color {
-            Color::White => self.can_castle_white_queenside,
-            Color::Black => self.can_castle_black_queenside,
-        }
+        self.castling_rook(color, false).is_some()
+    }
+    /// Returns where the rook that `color` can castle with on the kingside
+    /// (towards the h-file) or queenside is, if castling there is still
+    /// allowed.
+    pub fn castling_rook(&self, color: Color, kingside: bool) -> Option<Position> {
+        self.castling[castling_index(color, kingside)]
+            .map(|file| Position::new_unchecked(file, color.home_rank()))
     }
     /// Marks that `color` can no longer castle on the kingside. Can be called
     /// even if it was not possible before calling (but will have no effect)
     pub fn cannot_castle_kingside(&mut self, color: Color) {
-        match color {
-            Color::White => self.can_castle_white_kingside = false,
-            Color::Black => self.can_castle_black_kingside = false,
-        }
+        self.remove_castling_right(castling_index(color, true));
     }
     /// Marks that `color` can no longer castle on the queenside. Can be called
     /// even if it was not possible before calling (but will have no effect)
     pub fn cannot_castle_queenside(&mut self, color: Color) {
-        match color {
-            Color::White => self.can_castle_white_queenside = false,
-            Color::Black => self.can_castle_black_queenside = false,
+        self.remove_castling_right(castling_index(color, false));
+    }
+    fn remove_castling_right(&mut self, index: usize) {
+        if self.castling[index].take().is_some() {
+            self.hash ^= zobrist::castling_key(index);
         }
     }
+    /// Indicates if castling moves are written the Chess960 way, as the king
+    /// capturing its own rook. Otherwise they are written as the king moving
+    /// two files, which is the usual way in standard chess.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+    /// Returns `move_` the way castling moves are made on the board, i.e. as
+    /// the king capturing its own rook, if it's a castling move written as
+    /// the king moving two files. Other moves are returned as is.
+    ///
+    /// This is only done for standard chess, since the king may only move a
+    /// single file, or not at all, when castling in Chess960.
+    pub fn normalize_castling(&self, move_: Move) -> Move {
+        if self.chess960
+            || self[move_.from] != Some(Piece::new(self.next_to_move, piece::Kind::King))
+            || move_.from.rank() != move_.to.rank()
+        {
+            return move_;
+        }
+        let kingside = match (
+            move_.to.file() as i8 - move_.from.file() as i8,
+            move_.to.file(),
+        ) {
+            (2, 6) => true,
+            (-2, 2) => false,
+            _ => return move_,
+        };
+        match self.castling_rook(self.next_to_move, kingside) {
+            Some(rook) => Move { to: rook, ..move_ },
+            None => move_,
+        }
+    }
+    /// The opposite of `normalize_castling`: returns castling moves written
+    /// as the king moving to its destination, unless the board is Chess960.
+    pub fn display_castling(&self, move_: Move) -> Move {
+        if self.chess960 {
+            return move_;
+        }
+        match self.castling_side(move_) {
+            Some(kingside) => Move {
+                to: castling_king_target(move_.from, kingside),
+                ..move_
+            },
+            None => move_,
+        }
+    }
+    /// Returns whether `move_` is castling on the kingside or queenside, or
+    /// `None` if it's not castling. Castling moves are the king moving to
+    /// the tile of a rook it can castle with.
+    pub(crate) fn castling_side(&self, move_: Move) -> Option<bool> {
+        let color = self[move_.from]
+            .filter(|p| p.kind == piece::Kind::King)?
+            .color;
+        [true, false]
+            .iter()
+            .copied()
+            .find(|&kingside| self.castling_rook(color, kingside) == Some(move_.to))
+    }
     /// Sets the halvmove counter to zero
     pub fn reset_halfmove_counter(&mut self) {
         self.halfmove_counter = 0;
     }
     /// Returns the position of the king with the color `color`.
     pub fn get_king_position(&self, color: Color) -> Position {
-        let mut pos = Position::new_unchecked(0, 0);
-        while self[pos]
-            != Some(Piece {
-                color,
-                kind: piece::Kind::King,
-            })
-        {
-            if pos.file() == 7 {
-                pos = Position::new_unchecked(0, pos.rank() + 1)
-            } else {
-                pos = Position::new_unchecked(pos.file() + 1, pos.rank())
-            }
-        }
-        pos
+        self.by_piece(Piece::new(color, piece::Kind::King))
+            .first()
+            .expect("board has no king")
+    }
+}
+
+/// Index of a castling right in `Board::castling`.
+pub(crate) fn castling_index(color: Color, kingside: bool) -> usize {
+    color as usize * 2 + if kingside { 0 } else { 1 }
+}
+
+/// Where the king ends up when castling from `king`. The rook ends up on the
+/// other side of it.
+pub(crate) fn castling_king_target(king: Position, kingside: bool) -> Position {
+    Position::new_unchecked(if kingside { 6 } else { 2 }, king.rank())
+}
+
+/// Where the rook ends up when castling with the king on `king`.
+pub(crate) fn castling_rook_target(king: Position, kingside: bool) -> Position {
+    Position::new_unchecked(if kingside { 5 } else { 3 }, king.rank())
+}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.zobrist().hash(state);
     }
 }
 
@@ -106,12 +409,6 @@ impl ops::Index<Position> for Board {
     }
 }
 
-impl ops::IndexMut<Position> for Board {
-    fn index_mut(&mut self, p: Position) -> &mut Self::Output {
-        &mut self.tiles[p.rank() as usize][p.file() as usize]
-    }
-}
-
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(
@@ -0,0 +1,115 @@
+use std::ops;
+
+use crate::{bitboard, piece, Bitboard, Color, Position};
+
+use super::Board;
+
+/// A piece that can not leave the line between its king and an opponent's
+/// slider without exposing the king to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pin {
+    /// The tile of the pinned piece
+    pub pinned: Position,
+    /// The tile of the rook, bishop or queen pinning it
+    pub pinner: Position,
+    /// The tiles the pinned piece can move along without exposing its king:
+    /// those between the king and the pinner, and the tile of the pinner
+    pub line: Bitboard,
+}
+
+/// The pieces of one color attacking each tile, as returned by
+/// `Board::attack_map`. Indexing it with a tile gives the attackers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttackMap {
+    attackers: [Bitboard; 64],
+}
+
+impl AttackMap {
+    /// The number of pieces attacking `pos`.
+    pub fn count(&self, pos: Position) -> u32 {
+        self[pos].count()
+    }
+    /// All tiles attacked by at least one piece.
+    pub fn attacked(&self) -> Bitboard {
+        Bitboard::FULL
+            .into_iter()
+            .filter(|&pos| !self[pos].is_empty())
+            .fold(Bitboard::EMPTY, |acc, pos| acc | pos.into())
+    }
+}
+
+impl ops::Index<Position> for AttackMap {
+    type Output = Bitboard;
+    fn index(&self, pos: Position) -> &Self::Output {
+        &self.attackers[pos.index() as usize]
+    }
+}
+
+impl Board {
+    /// All pieces of `color` attacking `square`, whether or not they could
+    /// legally capture something there.
+    pub fn attackers_of(&self, square: Position, color: Color) -> Bitboard {
+        self.attackers(square, self.occupied()) & self.by_color(color)
+    }
+    /// The opponent's pieces giving check to the king of the player to move.
+    /// There are two of them after a double check.
+    pub fn checkers(&self) -> Bitboard {
+        let color = self.next_to_move;
+        self.attackers_of(self.get_king_position(color), color.other())
+    }
+    /// Indicates if the king of the player to move is in check.
+    pub fn is_check(&self) -> bool {
+        !self.checkers().is_empty()
+    }
+    /// All pieces of `color` that are pinned to their king by the opponent's
+    /// sliders.
+    pub fn pinned_pieces(&self, color: Color) -> Vec<Pin> {
+        use piece::Kind::*;
+        let king = self.get_king_position(color);
+        let enemies = self.by_color(color.other());
+        let diagonal = self.by_kind(Bishop) | self.by_kind(Queen);
+        let orthogonal = self.by_kind(Rook) | self.by_kind(Queen);
+        // Sliders that would attack the king if the board was empty
+        let sliders = (bitboard::bishop_attacks(king, Bitboard::EMPTY) & diagonal
+            | bitboard::rook_attacks(king, Bitboard::EMPTY) & orthogonal)
+            & enemies;
+
+        let mut pins = Vec::new();
+        for pinner in sliders {
+            let between = bitboard::between(king, pinner);
+            let blockers = between & self.occupied();
+            if blockers.count() == 1 && !(blockers & self.by_color(color)).is_empty() {
+                pins.push(Pin {
+                    pinned: blockers.first().unwrap(),
+                    pinner,
+                    line: between | pinner.into(),
+                });
+            }
+        }
+        pins
+    }
+    /// Returns which pieces of `color` attack each tile. Pieces only attack
+    /// up to the first piece in their way, so a rook behind a queen on the
+    /// same file does not count as attacking the tiles in front of the queen.
+    pub fn attack_map(&self, color: Color) -> AttackMap {
+        let mut attackers = [Bitboard::EMPTY; 64];
+        for from in self.by_color(color) {
+            for to in self[from].unwrap().attacks(self, from) {
+                attackers[to.index() as usize].insert(from);
+            }
+        }
+        AttackMap { attackers }
+    }
+    /// Returns, for each tile, the number of white pieces attacking it minus
+    /// the number of black pieces attacking it. Laid out like `tiles`.
+    pub fn control_map(&self) -> [[i8; 8]; 8] {
+        let white = self.attack_map(Color::White);
+        let black = self.attack_map(Color::Black);
+        let mut control = [[0; 8]; 8];
+        for pos in Bitboard::FULL {
+            control[pos.rank() as usize][pos.file() as usize] =
+                white.count(pos) as i8 - black.count(pos) as i8;
+        }
+        control
+    }
+}
@@ -2,103 +2,288 @@ use std::str::FromStr;
 
 use crate::{error::FenError, piece, Color, Error, Piece, Position};
 
-use super::Board;
+use super::{castling_index, Board};
+
+/// Splits `fen` into its whitespace separated fields, each with its offset
+/// in characters from the start.
+fn fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    // Offset in characters and bytes of the field being read
+    let mut start = None;
+    for (offset, (i, c)) in fen.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((offset, i)),
+            (true, Some((field_offset, field_start))) => {
+                fields.push((field_offset, &fen[field_start..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((field_offset, field_start)) = start {
+        fields.push((field_offset, &fen[field_start..]));
+    }
+    fields
+}
 
 impl Board {
+    /// Reads a board from Forsyth–Edwards Notation. All six fields are
+    /// required, and the position must be one that could occur in a game:
+    /// one king per player, no pawns on the first or last rank, the player
+    /// who just moved not in check, and castling rights and en passant tile
+    /// matching the pieces. Castling rights can be written as in standard
+    /// FEN, Shredder-FEN or X-FEN.
+    ///
+    /// Returns `Err(Error::FenError(_))` telling where and why reading failed
+    /// otherwise.
     pub fn from_fen(fen: &str) -> Result<Self, Error> {
-        let mut fen = fen.split_ascii_whitespace();
+        let fields = fields(fen);
+        let end = fen.chars().count();
+        let field = |i: usize, missing: fn(usize, &'static str) -> FenError| {
+            fields
+                .get(i)
+                .copied()
+                .ok_or_else(|| Error::FenError(missing(end, "missing field")))
+        };
+
+        let mut board = Board::empty();
+
+        let (offset, pieces) = field(0, |offset, reason| FenError::Pieces { offset, reason })?;
+        board.read_pieces(offset, pieces)?;
+
+        let (offset, next_to_move) =
+            field(1, |offset, reason| FenError::NextToMove { offset, reason })?;
+        board.next_to_move = match next_to_move {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => {
+                return Err(Error::FenError(FenError::NextToMove {
+                    offset,
+                    reason: "expected \"w\" or \"b\"",
+                }))
+            }
+        };
+        let waiting = board.next_to_move.other();
+        let waiting_king = board.get_king_position(waiting);
+        if !board
+            .attackers_of(waiting_king, board.next_to_move)
+            .is_empty()
+        {
+            return Err(Error::FenError(FenError::NextToMove {
+                offset,
+                reason: "the player not to move is in check",
+            }));
+        }
+
+        let (offset, castling) = field(2, |offset, reason| FenError::Castling { offset, reason })?;
+        board.read_castling(offset, castling)?;
+
+        let (offset, en_passant) =
+            field(3, |offset, reason| FenError::EnPassant { offset, reason })?;
+        board.read_en_passant(offset, en_passant)?;
 
-        let mut found_white_king = false;
-        let mut found_black_king = false;
+        let (offset, halfmove_counter) = field(4, |offset, reason| FenError::HalfmoveCounter {
+            offset,
+            reason,
+        })?;
+        board.halfmove_counter = halfmove_counter.parse().map_err(|_| {
+            Error::FenError(FenError::HalfmoveCounter {
+                offset,
+                reason: "not a number",
+            })
+        })?;
 
-        let mut tiles = [[None; 8]; 8];
+        let (offset, move_number) =
+            field(5, |offset, reason| FenError::MoveNumber { offset, reason })?;
+        board.move_number = match move_number.parse() {
+            Ok(0) => {
+                return Err(Error::FenError(FenError::MoveNumber {
+                    offset,
+                    reason: "must be at least 1",
+                }))
+            }
+            Ok(n) => n,
+            Err(_) => {
+                return Err(Error::FenError(FenError::MoveNumber {
+                    offset,
+                    reason: "not a number",
+                }))
+            }
+        };
+
+        if let Some(&(offset, _)) = fields.get(6) {
+            return Err(Error::FenError(FenError::TrailingInput {
+                offset,
+                reason: "unexpected input after the move number",
+            }));
+        }
 
-        let mut file = 0;
-        let mut rank = 0;
+        board.hash = board.compute_hash();
 
-        let tiles_part = fen.next().ok_or(Error::FenError(FenError::Pieces))?;
-        for c in tiles_part.chars() {
+        Ok(board)
+    }
+    /// Places the pieces of the piece placement field, which starts at
+    /// character `offset`.
+    fn read_pieces(&mut self, offset: usize, pieces: &str) -> Result<(), Error> {
+        let error = |i: usize, reason| {
+            Error::FenError(FenError::Pieces {
+                offset: offset + i,
+                reason,
+            })
+        };
+        let (mut file, mut rank) = (0u8, 0u8);
+        let mut after_digit = false;
+        let mut counts = [[0; 6]; 2];
+        let mut len = 0;
+        for (i, c) in pieces.chars().enumerate() {
+            len = i + 1;
             match c {
+                '/' if file < 8 => return Err(error(i, "too few tiles in rank")),
+                '/' if rank == 7 => return Err(error(i, "too many ranks")),
                 '/' => {
                     rank += 1;
                     file = 0;
                 }
+                '1'..='8' if after_digit => {
+                    return Err(error(i, "empty tiles not counted with a single digit"))
+                }
                 '1'..='8' => {
-                    file += c as usize - '0' as usize;
+                    file += c as u8 - b'0';
+                    if file > 8 {
+                        return Err(error(i, "too many tiles in rank"));
+                    }
                 }
                 _ => {
-                    let piece = Piece::from_name(c)?;
-                    if piece.kind == piece::Kind::King {
-                        *match piece.color {
-                            Color::White => &mut found_white_king,
-                            Color::Black => &mut found_black_king,
-                        } = true;
+                    let piece = Piece::from_name(c).map_err(|_| error(i, "unknown piece"))?;
+                    if file >= 8 {
+                        return Err(error(i, "too many tiles in rank"));
+                    }
+                    if piece.kind == piece::Kind::Pawn && (rank == 0 || rank == 7) {
+                        return Err(error(i, "pawn on the first or last rank"));
+                    }
+                    let count = &mut counts[piece.color as usize];
+                    count[piece.kind as usize] += 1;
+                    if count[piece::Kind::King as usize] > 1 {
+                        return Err(error(i, "more than one king of the same color"));
+                    }
+                    if count[piece::Kind::Pawn as usize] > 8 {
+                        return Err(error(i, "more than eight pawns of the same color"));
+                    }
+                    if count.iter().sum::<i32>() > 16 {
+                        return Err(error(i, "more than sixteen pieces of the same color"));
                     }
-                    tiles[rank][file] = Some(piece);
+                    self.put_piece(Position::new_unchecked(file, rank), piece);
                     file += 1;
                 }
             }
+            after_digit = c.is_ascii_digit();
         }
-
-        let mut board = Board {
-            tiles,
-            next_to_move: Color::White,
-            can_castle_white_kingside: false,
-            can_castle_white_queenside: false,
-            can_castle_black_kingside: false,
-            can_castle_black_queenside: false,
-            en_passant_square: None,
-            halfmove_counter: 0,
-            move_number: 0,
-        };
-
-        let next_to_move_part = fen.next().ok_or(Error::FenError(FenError::NextToMove))?;
-        board.next_to_move = match next_to_move_part {
-            "w" => Color::White,
-            "b" => Color::Black,
-            _ => return Err(Error::FenError(FenError::NextToMove)),
-        };
-
-        let castling_part = fen.next().ok_or(Error::FenError(FenError::Castling))?;
-        for c in castling_part.chars() {
-            match c {
-                'K' => board.can_castle_white_kingside = true,
-                'Q' => board.can_castle_white_queenside = true,
-                'k' => board.can_castle_black_kingside = true,
-                'q' => board.can_castle_black_queenside = true,
-                '-' => {}
-                _ => return Err(Error::FenError(FenError::Castling)),
+        if file < 8 {
+            return Err(error(len, "too few tiles in rank"));
+        }
+        if rank < 7 {
+            return Err(error(len, "too few ranks"));
+        }
+        for (color, name) in [
+            (Color::White, "missing white king"),
+            (Color::Black, "missing black king"),
+        ] {
+            if counts[color as usize][piece::Kind::King as usize] == 0 {
+                return Err(error(0, name));
             }
         }
-
-        let en_passant_square_part = fen.next().ok_or(Error::FenError(FenError::EnPassant))?;
-        board.en_passant_square = match en_passant_square_part {
-            "-" => None,
-            ep => match Position::from_str(ep) {
-                Ok(p) => Some(p),
-                Err(err) => return Err(err),
-            },
+        Ok(())
+    }
+    /// Sets the castling rights of the castling field, which starts at
+    /// character `offset`. "K" and "Q" mean the outermost rook on that side,
+    /// as in X-FEN, while Shredder-FEN and X-FEN name the file of the rook.
+    fn read_castling(&mut self, offset: usize, castling: &str) -> Result<(), Error> {
+        if castling == "-" {
+            return Ok(());
+        }
+        for (i, c) in castling.chars().enumerate() {
+            let error = |reason| {
+                Error::FenError(FenError::Castling {
+                    offset: offset + i,
+                    reason,
+                })
+            };
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let (king, rooks) = self.home_rank_castlers(color);
+            let file = match c.to_ascii_uppercase() {
+                'K' => king.and_then(|king| rooks.iter().rev().find(|&&f| f > king).copied()),
+                'Q' => king.and_then(|king| rooks.iter().find(|&&f| f < king).copied()),
+                'A'..='H' => {
+                    Some(c.to_ascii_uppercase() as u8 - b'A').filter(|f| rooks.contains(f))
+                }
+                _ => return Err(error("unknown castling right")),
+            };
+            let king = king.ok_or_else(|| error("king not on its home rank"))?;
+            let file = file.ok_or_else(|| error("no rook to castle with"))?;
+            let index = castling_index(color, file > king);
+            if self.castling[index].is_some() {
+                return Err(error("castling right given twice"));
+            }
+            self.castling[index] = Some(file);
+            if king != 4 || (file != 0 && file != 7) {
+                self.chess960 = true;
+            }
+        }
+        Ok(())
+    }
+    /// Sets the en passant tile of the en passant field, which starts at
+    /// character `offset`. There must be a pawn that could just have moved
+    /// two tiles past it.
+    fn read_en_passant(&mut self, offset: usize, en_passant: &str) -> Result<(), Error> {
+        if en_passant == "-" {
+            return Ok(());
+        }
+        let error = |i: usize, reason| {
+            Error::FenError(FenError::EnPassant {
+                offset: offset + i,
+                reason,
+            })
         };
-
-        let halfmove_counter_part = fen
-            .next()
-            .ok_or(Error::FenError(FenError::HalfmoveCounter))?;
-        board.halfmove_counter = halfmove_counter_part
-            .parse()
-            .map_err(|_| Error::FenError(FenError::HalfmoveCounter))?;
-
-        let move_number_part = fen.next().ok_or(Error::FenError(FenError::MoveNumber))?;
-        board.move_number = move_number_part
-            .parse()
-            .map_err(|_| Error::FenError(FenError::MoveNumber))?;
-
-        // TODO: Return error if game state is invalid
-        if !found_white_king || !found_black_king {
-            return Err(Error::InvalidGameState);
+        let ep = Position::from_str(en_passant).map_err(|_| error(0, "not a tile"))?;
+        let color = self.next_to_move;
+        // The tile the pawn passed over is on the third rank from the
+        // opponent's side
+        let expected_rank = color.other().home_rank() as i8 + 2 * color.backwards();
+        if ep.rank() as i8 != expected_rank {
+            return Err(error(1, "en passant tile on the wrong rank"));
         }
-
-        Ok(board)
+        let pawn = Position::new_i8_unchecked(ep.file() as i8, ep.rank() as i8 + color.backwards());
+        let start = Position::new_i8_unchecked(ep.file() as i8, ep.rank() as i8 + color.forwards());
+        if self[pawn] != Some(Piece::new(color.other(), piece::Kind::Pawn))
+            || self[ep].is_some()
+            || self[start].is_some()
+        {
+            return Err(error(0, "no pawn can just have moved past the tile"));
+        }
+        self.en_passant_square = Some(ep);
+        Ok(())
     }
+    /// Returns the file of the king of `color` if it's on its home rank, and
+    /// the files of all rooks of `color` on the home rank, from the a-file to
+    /// the h-file.
+    fn home_rank_castlers(&self, color: Color) -> (Option<u8>, Vec<u8>) {
+        let rank = color.home_rank();
+        let on_rank = |kind| {
+            (0..8).filter(move |&file| {
+                self[Position::new_unchecked(file, rank)] == Some(Piece::new(color, kind))
+            })
+        };
+        (
+            on_rank(piece::Kind::King).next(),
+            on_rank(piece::Kind::Rook).collect(),
+        )
+    }
+    /// Returns the board in Forsyth–Edwards Notation. Castling rights are
+    /// written as in X-FEN, so that Chess960 positions can be read back.
     pub fn to_fen(&self) -> String {
         let mut fen = String::new();
 
@@ -115,10 +300,10 @@ impl Board {
                     skip += 1;
                 }
             }
+            if skip != 0 {
+                fen.push_str(&format!("{}", skip));
+            }
             if rank != 7 {
-                if skip != 0 {
-                    fen.push_str(&format!("{}", skip));
-                }
                 fen.push('/');
             }
         }
@@ -132,24 +317,37 @@ impl Board {
 
         fen.push(' ');
 
-        if !self.can_castle_white_kingside
-            && !self.can_castle_white_queenside
-            && !self.can_castle_black_kingside
-            && !self.can_castle_black_queenside
-        {
+        if self.castling.iter().all(Option::is_none) {
             fen.push('-');
         }
-        if self.can_castle_white_kingside {
-            fen.push('K');
-        }
-        if self.can_castle_white_queenside {
-            fen.push('Q');
-        }
-        if self.can_castle_black_kingside {
-            fen.push('k');
-        }
-        if self.can_castle_black_queenside {
-            fen.push('q');
+        // Written as X-FEN, which is the same as standard FEN unless there
+        // is a rook further out than the one that can castle
+        for color in [Color::White, Color::Black] {
+            let (_, rooks) = self.home_rank_castlers(color);
+            for kingside in [true, false] {
+                let file = match self.castling[castling_index(color, kingside)] {
+                    Some(file) => file,
+                    None => continue,
+                };
+                let outermost = if kingside {
+                    rooks.last()
+                } else {
+                    rooks.first()
+                };
+                let c = if outermost == Some(&file) {
+                    if kingside {
+                        'K'
+                    } else {
+                        'Q'
+                    }
+                } else {
+                    (b'A' + file) as char
+                };
+                fen.push(match color {
+                    Color::White => c,
+                    Color::Black => c.to_ascii_lowercase(),
+                });
+            }
         }
 
         fen.push(' ');
@@ -157,7 +355,7 @@ impl Board {
         if let Some(ep) = self.en_passant_square {
             fen.push_str(&ep.to_string());
         } else {
-            fen.push_str("-");
+            fen.push('-');
         }
 
         fen.push(' ');
@@ -0,0 +1,206 @@
+use crate::{
+    bitboard, error::IllegalMoveReason, piece, piece::util::pinner, Bitboard, Move, Piece, Position,
+};
+
+use super::{castling_king_target, castling_rook_target, Board};
+
+/// The number of king steps between `a` and `b`.
+fn distance(a: Position, b: Position) -> u8 {
+    let files = (a.file() as i8 - b.file() as i8).unsigned_abs();
+    let ranks = (a.rank() as i8 - b.rank() as i8).unsigned_abs();
+    files.max(ranks)
+}
+
+impl Board {
+    /// Returns why `move_` is illegal for the piece on `move_.from`, or
+    /// `None` if the move is legal or there is no piece of the player to
+    /// move to make it. Castling may be written either way, as with
+    /// `Game::make_move`.
+    ///
+    /// # Example use:
+    /// ```rust
+    /// # use chess_engine::{error::IllegalMoveReason, Board, Move, Position};
+    /// let board = Board::from_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+    /// let bishop_move = Move::arabic("e2d3").unwrap();
+    /// assert_eq!(
+    ///     Some(IllegalMoveReason::Pinned { pinner: "e7".parse().unwrap() }),
+    ///     board.explain_illegal(bishop_move)
+    /// );
+    /// ```
+    pub fn explain_illegal(&self, move_: Move) -> Option<IllegalMoveReason> {
+        use IllegalMoveReason::*;
+
+        let move_ = self.normalize_castling(move_);
+        let color = self.next_to_move;
+        let piece = self[move_.from].filter(|p| p.color == color)?;
+        let (from, to) = (move_.from, move_.to);
+        if piece.targets(self, from).contains(to) {
+            let promotes =
+                piece.kind == piece::Kind::Pawn && to.rank() == color.other().home_rank();
+            return match move_.promotion {
+                Some(kind) if promotes && !kind.is_valid_promotion() => Some(InvalidPromotion),
+                _ => None,
+            };
+        }
+
+        let occupied = self.occupied();
+        let enemies = self.by_color(color.other());
+        let checkers = self.checkers();
+
+        if piece.kind == piece::Kind::King
+            && from.rank() == color.home_rank()
+            && to.rank() == from.rank()
+        {
+            // In standard chess, castling can also be written as the king
+            // moving two files, and the rooks start in the corners
+            let castles = if self.chess960 {
+                self[to] == Some(Piece::new(color, piece::Kind::Rook))
+            } else {
+                from.file() == 4 && [0, 2, 6, 7].contains(&to.file())
+            };
+            if castles {
+                return Some(self.explain_castling(from, to, checkers));
+            }
+        }
+        if from == to {
+            return Some(Unreachable);
+        }
+        if self[to].is_some_and(|p| p.color == color) {
+            return Some(OwnPiece);
+        }
+
+        let on_empty_board = match piece.kind {
+            piece::Kind::Pawn => return Some(self.explain_pawn(from, to, checkers)),
+            piece::Kind::Knight => bitboard::knight_attacks(from),
+            piece::Kind::Bishop => bitboard::bishop_attacks(from, Bitboard::EMPTY),
+            piece::Kind::Rook => bitboard::rook_attacks(from, Bitboard::EMPTY),
+            piece::Kind::Queen => bitboard::queen_attacks(from, Bitboard::EMPTY),
+            piece::Kind::King => bitboard::king_attacks(from),
+        };
+        if !on_empty_board.contains(to) {
+            return Some(Unreachable);
+        }
+        if let Some(by) = self.first_blocker(from, to) {
+            return Some(Blocked { by });
+        }
+        if piece.kind == piece::Kind::King {
+            let attackers = self.attackers(to, occupied ^ from.into()) & enemies;
+            return Some(IntoCheck { attackers });
+        }
+        Some(self.explain_king_safety(from, to, checkers))
+    }
+    /// The piece closest to `from` on the way to `to`, if any.
+    fn first_blocker(&self, from: Position, to: Position) -> Option<Position> {
+        (bitboard::between(from, to) & self.occupied())
+            .into_iter()
+            .min_by_key(|&pos| distance(from, pos))
+    }
+    /// Explains an illegal move of a piece other than the king that could
+    /// reach `to` if it weren't for its own king being in check afterwards.
+    fn explain_king_safety(
+        &self,
+        from: Position,
+        to: Position,
+        checkers: Bitboard,
+    ) -> IllegalMoveReason {
+        let king = self.get_king_position(self.next_to_move);
+        let resolves_check = match checkers.first() {
+            Some(checker) if checkers.count() == 1 => {
+                (bitboard::between(king, checker) | checker.into()).contains(to)
+            }
+            Some(_) => false,
+            None => true,
+        };
+        if !resolves_check {
+            return IllegalMoveReason::InCheck { checkers };
+        }
+        match pinner(self, from, self.next_to_move) {
+            Some(pinner) => IllegalMoveReason::Pinned { pinner },
+            None => IllegalMoveReason::Unreachable,
+        }
+    }
+    fn explain_pawn(&self, from: Position, to: Position, checkers: Bitboard) -> IllegalMoveReason {
+        use IllegalMoveReason::*;
+
+        let color = self.next_to_move;
+        let step = color.forwards();
+        let ranks = to.rank() as i8 - from.rank() as i8;
+        if bitboard::pawn_attacks(from, color).contains(to) {
+            if self[to].is_some() {
+                return self.explain_king_safety(from, to, checkers);
+            }
+            if Some(to) != self.en_passant_square {
+                return NothingToCapture;
+            }
+            // Both pawns leave their tiles, which may uncover an attack on
+            // the king along the rank
+            let captured = Position::new_i8_unchecked(to.file() as i8, from.rank() as i8);
+            let king = self.get_king_position(color);
+            let occupied = self.occupied() ^ from.into() ^ captured.into() ^ to.into();
+            let attackers = self.attackers(king, occupied)
+                & self.by_color(color.other())
+                & !Bitboard::from(captured);
+            let discovered = attackers & !checkers;
+            return match discovered.first() {
+                Some(pinner) => Pinned { pinner },
+                None => InCheck { checkers },
+            };
+        }
+
+        let double_step = from.rank() == color.home_pawn_rank() && ranks == 2 * step;
+        if to.file() != from.file() || !(ranks == step || double_step) {
+            return Unreachable;
+        }
+        let blockers = (bitboard::between(from, to) | to.into()) & self.occupied();
+        match blockers.into_iter().min_by_key(|&pos| distance(from, pos)) {
+            Some(by) => Blocked { by },
+            None => self.explain_king_safety(from, to, checkers),
+        }
+    }
+    /// Explains why the king on `king` can not castle by moving to `to`,
+    /// which is either the tile of a rook or two files to the side.
+    fn explain_castling(
+        &self,
+        king: Position,
+        to: Position,
+        checkers: Bitboard,
+    ) -> IllegalMoveReason {
+        use IllegalMoveReason::*;
+
+        let kingside = to.file() > king.file();
+        let rook = match self.castling_rook(self.next_to_move, kingside) {
+            Some(rook) if rook == to => rook,
+            _ => return NoCastlingRight,
+        };
+        if !checkers.is_empty() {
+            return InCheck { checkers };
+        }
+        let king_to = castling_king_target(king, kingside);
+        let rook_to = castling_rook_target(king, kingside);
+        let castlers = Bitboard::from(king) | rook.into();
+        let king_path = bitboard::between(king, king_to) | king_to.into();
+        let rook_path = bitboard::between(rook, rook_to) | rook_to.into();
+        let blockers = (king_path | rook_path) & self.occupied() & !castlers;
+        if let Some(by) = blockers.into_iter().min_by_key(|&pos| distance(king, pos)) {
+            return Blocked { by };
+        }
+
+        let enemies = self.by_color(self.next_to_move.other());
+        let occupied = self.occupied() ^ king.into();
+        let mut passed = bitboard::between(king, king_to)
+            .into_iter()
+            .collect::<Vec<_>>();
+        passed.sort_by_key(|&tile| distance(king, tile));
+        for tile in passed {
+            let attackers = self.attackers(tile, occupied) & enemies;
+            if !attackers.is_empty() {
+                return CastlingThroughCheck { tile, attackers };
+            }
+        }
+        let occupied = (self.occupied() & !castlers) | rook_to.into();
+        CastlingThroughCheck {
+            tile: king_to,
+            attackers: self.attackers(king_to, occupied) & enemies,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{piece, Color, Move, Piece, Position};
+
+use super::{castling_king_target, castling_rook_target, Board};
+
+/// The information needed to take back a move made with
+/// `Board::make_move_unchecked`, i.e. everything about the board that can not
+/// be derived from the move itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Undo {
+    pub(crate) captured: Option<Piece>,
+    /// Whether a pawn was promoted, since the move may have `promotion` set
+    /// without it being a pawn move
+    pub(crate) promoted: bool,
+    pub(crate) castling: [Option<u8>; 4],
+    pub(crate) en_passant_square: Option<Position>,
+    pub(crate) halfmove_counter: u16,
+    pub(crate) hash: u64,
+}
+
+impl Undo {
+    /// The piece that was captured by the move, if any
+    pub fn captured(&self) -> Option<Piece> {
+        self.captured
+    }
+}
+
+impl Board {
+    /// Which castling rights are left, in the order white kingside, white
+    /// queenside, black kingside, black queenside.
+    pub(super) fn castling_rights(&self) -> [bool; 4] {
+        self.castling.map(|file| file.is_some())
+    }
+    /// Removes the castling right with the rook on `pos`, if any.
+    fn rook_gone(&mut self, pos: Position) {
+        for color in [Color::White, Color::Black] {
+            if self.castling_rook(color, true) == Some(pos) {
+                self.cannot_castle_kingside(color);
+            }
+            if self.castling_rook(color, false) == Some(pos) {
+                self.cannot_castle_queenside(color);
+            }
+        }
+    }
+    /// The tile directly behind `pos` as seen by `color`. For a pawn capturing
+    /// en passant this is where the captured pawn is, and for a pawn moving
+    /// two steps it is the new en passant square.
+    fn behind(pos: Position, color: Color) -> Position {
+        Position::new_i8_unchecked(pos.file() as i8, pos.rank() as i8 + color.backwards())
+    }
+    /// Makes the move without checking that it is legal, returning what is
+    /// needed to take it back with `unmake_move`.
+    ///
+    /// The move must be legal, and `promotion` must be set to a kind a pawn
+    /// can be promoted to if and only if a pawn reaches the last rank,
+    /// otherwise the board may end up in an invalid state.
+    pub fn make_move_unchecked(&mut self, move_: Move) -> Undo {
+        let hash = self.hash;
+        let castling_side = self.castling_side(move_);
+        let castling = self.castling;
+        let piece = self.remove_piece(move_.from).unwrap();
+        let color = piece.color;
+
+        // Handle castling, where the king "captures" its own rook
+        if let Some(kingside) = castling_side {
+            let rook = self.remove_piece(move_.to).unwrap();
+            self.put_piece(castling_king_target(move_.from, kingside), piece);
+            self.put_piece(castling_rook_target(move_.from, kingside), rook);
+        }
+
+        let mut undo = Undo {
+            captured: None,
+            promoted: false,
+            castling,
+            en_passant_square: self.en_passant_square,
+            halfmove_counter: self.halfmove_counter,
+            hash,
+        };
+
+        if castling_side.is_none() {
+            undo.captured = self.remove_piece(move_.to);
+            // Handle promotion
+            let placed = match move_.promotion {
+                Some(kind) if piece.kind == piece::Kind::Pawn => {
+                    undo.promoted = true;
+                    Piece::new(color, kind)
+                }
+                _ => piece,
+            };
+            self.put_piece(move_.to, placed);
+        }
+
+        // Handle castling marking
+        if piece.kind == piece::Kind::King {
+            self.cannot_castle_kingside(color);
+            self.cannot_castle_queenside(color);
+        }
+        self.rook_gone(move_.from);
+        self.rook_gone(move_.to);
+
+        // Handle en passant capture
+        if piece.kind == piece::Kind::Pawn && Some(move_.to) == self.en_passant_square {
+            undo.captured = self.remove_piece(Self::behind(move_.to, color));
+        }
+
+        // Handle en passant marking
+        let delta_rank = move_.to.rank() as i8 - move_.from.rank() as i8;
+        if piece.kind == piece::Kind::Pawn && delta_rank.abs() == 2 {
+            self.set_en_passant_square(Some(Self::behind(move_.to, color)));
+        } else {
+            self.set_en_passant_square(None);
+        }
+
+        self.switch_next_to_move();
+        if undo.captured.is_some() || piece.kind == piece::Kind::Pawn {
+            self.reset_halfmove_counter();
+        }
+
+        undo
+    }
+    /// Takes back `move_`, which must be the last move made on the board with
+    /// `make_move_unchecked` which returned `undo`.
+    pub fn unmake_move(&mut self, move_: Move, undo: Undo) {
+        self.next_to_move = self.next_to_move.other();
+        if self.next_to_move == Color::Black {
+            self.move_number -= 1;
+        }
+        self.halfmove_counter = undo.halfmove_counter;
+        self.en_passant_square = undo.en_passant_square;
+        self.castling = undo.castling;
+
+        let color = self.next_to_move;
+        let castling_side = [true, false].iter().copied().find(|&kingside| {
+            self.castling_rook(color, kingside) == Some(move_.to)
+                && self[castling_king_target(move_.from, kingside)]
+                    == Some(Piece::new(color, piece::Kind::King))
+        });
+        if let Some(kingside) = castling_side {
+            let king = self
+                .remove_piece(castling_king_target(move_.from, kingside))
+                .unwrap();
+            let rook = self
+                .remove_piece(castling_rook_target(move_.from, kingside))
+                .unwrap();
+            self.put_piece(move_.from, king);
+            self.put_piece(move_.to, rook);
+            self.hash = undo.hash;
+            return;
+        }
+
+        let mut piece = self.remove_piece(move_.to).unwrap();
+        if undo.promoted {
+            piece = Piece::new(color, piece::Kind::Pawn);
+        }
+        self.put_piece(move_.from, piece);
+
+        if let Some(captured) = undo.captured {
+            if piece.kind == piece::Kind::Pawn && Some(move_.to) == undo.en_passant_square {
+                self.put_piece(Self::behind(move_.to, color), captured);
+            } else {
+                self.put_piece(move_.to, captured);
+            }
+        }
+
+        self.hash = undo.hash;
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{piece::Kind, Color, Move, Position};
+
+use super::Board;
+
+/// The kinds of pieces in the order they are used for recapturing.
+const LEAST_VALUABLE_FIRST: [Kind; 6] = [
+    Kind::Pawn,
+    Kind::Knight,
+    Kind::Bishop,
+    Kind::Rook,
+    Kind::Queen,
+    Kind::King,
+];
+
+impl Board {
+    /// Static exchange evaluation: the material in centipawns the player to
+    /// move wins with `move_` if both players then keep recapturing on its
+    /// destination with their least valuable piece for as long as it pays
+    /// off. Pieces behind others on the same line join in as soon as the way
+    /// is clear, and pawns reaching the last rank are promoted to queens.
+    ///
+    /// Pins and checks are not taken into account. Castling and moves
+    /// without a piece to make them are worth 0.
+    ///
+    /// # Example use:
+    /// ```rust
+    /// # use chess_engine::{Board, Move};
+    /// let board = Board::from_fen("4k3/8/3p4/4p3/8/5N2/8/4K3 w - - 0 1").unwrap();
+    /// // The knight takes a pawn, but is then taken by the other pawn
+    /// assert_eq!(100 - 320, board.see(Move::arabic("f3e5").unwrap()));
+    /// ```
+    pub fn see(&self, move_: Move) -> i32 {
+        let move_ = self.normalize_castling(move_);
+        let piece = match self[move_.from] {
+            Some(piece) if self.castling_side(move_).is_none() => piece,
+            _ => return 0,
+        };
+        let to = move_.to;
+        let promotes =
+            |kind, color: Color| kind == Kind::Pawn && to.rank() == color.other().home_rank();
+
+        let mut occupied = self.occupied() ^ move_.from.into();
+        // What each capture wins, assuming the piece that made it is lost
+        // to the next one
+        let mut gains = Vec::with_capacity(32);
+        let mut gain = match self[to] {
+            Some(captured) => captured.kind.value(),
+            None if piece.kind == Kind::Pawn && Some(to) == self.en_passant_square => {
+                let captured = Position::new_i8_unchecked(to.file() as i8, move_.from.rank() as i8);
+                occupied ^= captured.into();
+                Kind::Pawn.value()
+            }
+            None => 0,
+        };
+        let mut on_target = piece.kind;
+        if promotes(piece.kind, piece.color) {
+            on_target = move_
+                .promotion
+                .filter(Kind::is_valid_promotion)
+                .unwrap_or(Kind::Queen);
+            gain += on_target.value() - Kind::Pawn.value();
+        }
+        gains.push(gain);
+
+        let mut color = piece.color.other();
+        loop {
+            // Only pieces still on the board can recapture, and removing
+            // pieces uncovers the sliders behind them
+            let attackers = self.attackers(to, occupied) & occupied;
+            let own = attackers & self.by_color(color);
+            let (from, kind) = match LEAST_VALUABLE_FIRST
+                .iter()
+                .find_map(|&kind| (own & self.by_kind(kind)).first().map(|pos| (pos, kind)))
+            {
+                Some(attacker) => attacker,
+                None => break,
+            };
+            // The king can only recapture if nothing can take it back
+            if kind == Kind::King && !(attackers & self.by_color(color.other())).is_empty() {
+                break;
+            }
+
+            let mut gain = on_target.value() - gains.last().unwrap();
+            on_target = kind;
+            if promotes(kind, color) {
+                on_target = Kind::Queen;
+                gain += Kind::Queen.value() - Kind::Pawn.value();
+            }
+            gains.push(gain);
+            occupied ^= from.into();
+            color = color.other();
+        }
+
+        // Each player can stop recapturing when it would lose material
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+        gains[0]
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{Color, Piece, Position};
+
+use super::Board;
+
+/// Random keys combined with xor to form the hash of a position.
+struct Keys {
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+/// SplitMix64, used to fill the key tables at compile time.
+const fn next_key(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> Keys {
+    let mut state = 0x4d61_7468_6d43_6873;
+    let mut keys = Keys {
+        pieces: [[[0; 64]; 6]; 2],
+        black_to_move: 0,
+        castling: [0; 4],
+        en_passant_file: [0; 8],
+    };
+    let mut color = 0;
+    while color < 2 {
+        let mut kind = 0;
+        while kind < 6 {
+            let mut sq = 0;
+            while sq < 64 {
+                keys.pieces[color][kind][sq] = next_key(&mut state);
+                sq += 1;
+            }
+            kind += 1;
+        }
+        color += 1;
+    }
+    keys.black_to_move = next_key(&mut state);
+    let mut i = 0;
+    while i < 4 {
+        keys.castling[i] = next_key(&mut state);
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        keys.en_passant_file[i] = next_key(&mut state);
+        i += 1;
+    }
+    keys
+}
+
+static KEYS: Keys = generate_keys();
+
+pub(super) fn piece_key(piece: Piece, pos: Position) -> u64 {
+    KEYS.pieces[piece.color as usize][piece.kind as usize][pos.index() as usize]
+}
+
+pub(super) fn black_to_move_key() -> u64 {
+    KEYS.black_to_move
+}
+
+/// Key for a castling right, indexed in the same order as
+/// `Board::castling_rights`.
+pub(super) fn castling_key(index: usize) -> u64 {
+    KEYS.castling[index]
+}
+
+impl Board {
+    /// Returns the Zobrist hash of the position. Two boards with the same
+    /// pieces on the same tiles, the same player to move and the same
+    /// castling and en passant possibilities have the same hash, regardless
+    /// of move counters.
+    ///
+    /// The hash is updated incrementally as moves are made, so this is cheap
+    /// to call.
+    pub fn zobrist(&self) -> u64 {
+        match self.capturable_en_passant_square() {
+            Some(ep) => self.hash ^ KEYS.en_passant_file[ep.file() as usize],
+            None => self.hash,
+        }
+    }
+    /// Computes the incrementally updated part of the hash from scratch.
+    pub(crate) fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for pos in self.occupied() {
+            hash ^= piece_key(self[pos].unwrap(), pos);
+        }
+        if self.next_to_move == Color::Black {
+            hash ^= KEYS.black_to_move;
+        }
+        for (i, &right) in self.castling_rights().iter().enumerate() {
+            if right {
+                hash ^= KEYS.castling[i];
+            }
+        }
+        hash
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{Color, Error};
+
+/// Where a `Clock` gets the current time from. Only differences between
+/// times are used, so the epoch can be anything.
+pub trait TimeSource: fmt::Debug + Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+/// Real time, as measured by `Instant`.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemTimeSource(Instant);
+
+impl SystemTimeSource {
+    pub fn new() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// Time that only passes when told to, for deterministic tests.
+///
+/// # Example use:
+/// ```rust
+/// # use std::{sync::Arc, time::Duration};
+/// # use chess_engine::{clock::{Clock, ManualTimeSource}, Color};
+/// let time = Arc::new(ManualTimeSource::default());
+/// let mut clock = Clock::with_time_source("5+3".parse().unwrap(), time.clone());
+/// clock.start(Color::White);
+/// time.advance(Duration::from_secs(10));
+/// clock.press();
+/// assert_eq!(Duration::from_secs(5 * 60 - 10 + 3), clock.remaining(Color::White));
+/// ```
+#[derive(Debug, Default)]
+pub struct ManualTimeSource(AtomicU64);
+
+impl ManualTimeSource {
+    pub fn advance(&self, duration: Duration) {
+        self.0
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// How the extra time of a `Period` is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Increment {
+    /// The time is added after every move
+    Fischer,
+    /// The time spent on a move is given back, but at most this much
+    Bronstein,
+    /// The clock waits this long before starting to count down on each move
+    /// (US delay)
+    Delay,
+}
+
+/// A part of a time control, e.g. "40 moves in 90 minutes with 30 seconds
+/// added per move".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    /// Number of moves to be made in the period, or `None` if it lasts for
+    /// the rest of the game
+    pub moves: Option<u32>,
+    /// Time added to the clock when the period starts
+    pub time: Duration,
+    pub increment: Duration,
+    pub increment_kind: Increment,
+}
+
+/// One or more periods, played in order. If the last period has a number of
+/// moves, it is repeated.
+///
+/// Time controls can be parsed from strings like "5+3" (five minutes, three
+/// seconds Fischer increment) or "40/90+30, then 30+30" (40 moves in 90
+/// minutes, then 30 minutes for the rest of the game, all with 30 seconds
+/// increment). The increment can be written as "d5" for a five second delay
+/// or "b5" for a five second Bronstein delay instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    pub periods: Vec<Period>,
+}
+
+impl TimeControl {
+    /// A single period of `time` for the whole game, without increment.
+    pub fn sudden_death(time: Duration) -> Self {
+        Self {
+            periods: vec![Period {
+                moves: None,
+                time,
+                increment: Duration::ZERO,
+                increment_kind: Increment::Fischer,
+            }],
+        }
+    }
+    /// Returns the period a player is in after `index` periods have ended.
+    fn period(&self, index: usize) -> &Period {
+        &self.periods[index.min(self.periods.len() - 1)]
+    }
+}
+
+impl FromStr for Period {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (moves, rest) = match s.split_once('/') {
+            Some((moves, rest)) => (
+                Some(moves.trim().parse().map_err(|_| Error::ParsingError)?),
+                rest,
+            ),
+            None => (None, s),
+        };
+        let (time, increment, increment_kind) = match rest.find(['+', 'd', 'b']) {
+            Some(i) => {
+                let kind = match &rest[i..=i] {
+                    "+" => Increment::Fischer,
+                    "d" => Increment::Delay,
+                    _ => Increment::Bronstein,
+                };
+                (&rest[..i], &rest[i + 1..], kind)
+            }
+            None => (rest, "0", Increment::Fischer),
+        };
+        let parse = |s: &str, unit: f64| {
+            s.trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(|n| Duration::try_from_secs_f64(n * unit).ok())
+                .ok_or(Error::ParsingError)
+        };
+        if moves == Some(0) {
+            return Err(Error::ParsingError);
+        }
+        Ok(Self {
+            moves,
+            time: parse(time, 60.0)?,
+            increment: parse(increment, 1.0)?,
+            increment_kind,
+        })
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let periods = s
+            .split(',')
+            .map(|p| {
+                let p = p.trim();
+                p.strip_prefix("then").unwrap_or(p).parse()
+            })
+            .collect::<Result<Vec<Period>, _>>()?;
+        if periods.is_empty() {
+            return Err(Error::ParsingError);
+        }
+        Ok(Self { periods })
+    }
+}
+
+/// A chess clock, keeping track of the time left for both players.
+///
+/// The clock runs for one player at a time, from `start` until `press` is
+/// called after their move, which starts it for the opponent.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    source: Arc<dyn TimeSource>,
+    /// Time left for each player, not counting the current turn
+    remaining: [Duration; 2],
+    /// How many periods each player has completed
+    periods: [usize; 2],
+    /// Moves made by each player in their current period
+    moves: [u32; 2],
+    /// The player whose time is running, and when their turn started
+    running: Option<(Color, Duration)>,
+}
+
+impl Clock {
+    /// Creates a stopped clock measuring real time.
+    pub fn new(control: TimeControl) -> Self {
+        Self::with_time_source(control, Arc::new(SystemTimeSource::new()))
+    }
+    /// Creates a stopped clock getting the time from `source`.
+    pub fn with_time_source(control: TimeControl, source: Arc<dyn TimeSource>) -> Self {
+        let time = control.period(0).time;
+        Self {
+            control,
+            source,
+            remaining: [time; 2],
+            periods: [0; 2],
+            moves: [0; 2],
+            running: None,
+        }
+    }
+    pub fn time_control(&self) -> &TimeControl {
+        &self.control
+    }
+    /// The player whose time is running, if the clock is running.
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+    /// Starts the time of `color`, stopping the other player's time without
+    /// giving them any increment.
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some((color, self.source.now()));
+    }
+    /// Stops the clock. Time used so far in the current turn is deducted.
+    pub fn stop(&mut self) {
+        if let Some((color, _)) = self.running {
+            self.remaining[color as usize] = self.remaining(color);
+            self.running = None;
+        }
+    }
+    /// Time used by `color` so far in the current turn that counts against
+    /// their remaining time.
+    fn used(&self, color: Color) -> Duration {
+        match self.running {
+            Some((running, start)) if running == color => {
+                let elapsed = self.source.now().saturating_sub(start);
+                let period = self.control.period(self.periods[color as usize]);
+                match period.increment_kind {
+                    Increment::Delay => elapsed.saturating_sub(period.increment),
+                    Increment::Fischer | Increment::Bronstein => elapsed,
+                }
+            }
+            _ => Duration::ZERO,
+        }
+    }
+    /// Time left for `color`, counting the current turn.
+    pub fn remaining(&self, color: Color) -> Duration {
+        self.remaining[color as usize].saturating_sub(self.used(color))
+    }
+    /// Returns the player whose time has run out, if any.
+    pub fn flagged(&self) -> Option<Color> {
+        [Color::White, Color::Black]
+            .iter()
+            .copied()
+            .find(|&color| self.remaining(color).is_zero())
+    }
+    /// Ends the turn of the running player after they have made a move,
+    /// giving them their increment and starting the opponent's time. Does
+    /// nothing if the clock is stopped or the player has run out of time.
+    pub fn press(&mut self) {
+        let (color, start) = match self.running {
+            Some(running) => running,
+            None => return,
+        };
+        if self.remaining(color).is_zero() {
+            return;
+        }
+        let elapsed = self.source.now().saturating_sub(start);
+        let i = color as usize;
+        let period = *self.control.period(self.periods[i]);
+        self.remaining[i] = self.remaining(color);
+        self.remaining[i] += match period.increment_kind {
+            Increment::Fischer => period.increment,
+            Increment::Bronstein => elapsed.min(period.increment),
+            Increment::Delay => Duration::ZERO,
+        };
+
+        self.moves[i] += 1;
+        if period.moves == Some(self.moves[i]) {
+            self.periods[i] += 1;
+            self.moves[i] = 0;
+            self.remaining[i] += self.control.period(self.periods[i]).time;
+        }
+
+        self.running = None;
+        self.start(color.other());
+    }
+}
@@ -1,26 +1,136 @@
 use std::error::Error as StdError;
 use std::fmt;
 
+use crate::{Bitboard, Position};
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
     OtherPlayersTurn,
     NoPieceToMove,
     IllegalMove,
+    AmbiguousMove,
     UnknwonPiece(char),
     ParsingError,
     FenError(FenError),
+    PgnError(PgnError),
     InvalidGameState,
     RequiresPromotion,
+    NoDrawToClaim,
+    NoDrawOffer,
+    GameOver,
 }
 
+/// Describes where and why reading a FEN failed. The variant is the field
+/// the error was found in, `offset` is the position in characters of the
+/// offending part of the input, and `reason` says what is wrong with it.
 #[derive(Debug, PartialEq, Eq)]
 pub enum FenError {
-    Pieces,
-    NextToMove,
-    Castling,
-    EnPassant,
-    HalfmoveCounter,
-    MoveNumber,
+    Pieces {
+        offset: usize,
+        reason: &'static str,
+    },
+    NextToMove {
+        offset: usize,
+        reason: &'static str,
+    },
+    Castling {
+        offset: usize,
+        reason: &'static str,
+    },
+    EnPassant {
+        offset: usize,
+        reason: &'static str,
+    },
+    HalfmoveCounter {
+        offset: usize,
+        reason: &'static str,
+    },
+    MoveNumber {
+        offset: usize,
+        reason: &'static str,
+    },
+    /// There is more input after the move number
+    TrailingInput {
+        offset: usize,
+        reason: &'static str,
+    },
+}
+
+impl FenError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            Self::Pieces { offset, .. }
+            | Self::NextToMove { offset, .. }
+            | Self::Castling { offset, .. }
+            | Self::EnPassant { offset, .. }
+            | Self::HalfmoveCounter { offset, .. }
+            | Self::MoveNumber { offset, .. }
+            | Self::TrailingInput { offset, .. } => offset,
+        }
+    }
+    pub fn reason(&self) -> &'static str {
+        match *self {
+            Self::Pieces { reason, .. }
+            | Self::NextToMove { reason, .. }
+            | Self::Castling { reason, .. }
+            | Self::EnPassant { reason, .. }
+            | Self::HalfmoveCounter { reason, .. }
+            | Self::MoveNumber { reason, .. }
+            | Self::TrailingInput { reason, .. } => reason,
+        }
+    }
+}
+
+/// Describes where and why reading a PGN failed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PgnError {
+    /// The ply, counting from 1, of the move being read when the error
+    /// occurred. 0 if the error was in the tag pairs.
+    pub ply: usize,
+    /// The token that could not be read
+    pub token: String,
+    pub reason: PgnErrorReason,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PgnErrorReason {
+    MalformedTag,
+    InvalidFen(Box<Error>),
+    UnterminatedComment,
+    UnterminatedVariation,
+    UnexpectedToken,
+    Move(Box<Error>),
+}
+
+/// Why a move that the piece on its starting tile wants to make is illegal,
+/// as found by `Game::explain_illegal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMoveReason {
+    /// The piece can not move like that, even on an empty board
+    Unreachable,
+    /// The destination is occupied by a piece of the same color
+    OwnPiece,
+    /// Another piece stands in the way, the one closest to the moving piece
+    /// being at `by`
+    Blocked { by: Position },
+    /// A pawn moving diagonally has nothing to capture
+    NothingToCapture,
+    /// The piece is pinned to its king by the piece at `pinner`, and the
+    /// move would leave the line between them
+    Pinned { pinner: Position },
+    /// The king is in check by `checkers`, and the move does not get it out
+    /// of check
+    InCheck { checkers: Bitboard },
+    /// The king would move to a tile attacked by `attackers`
+    IntoCheck { attackers: Bitboard },
+    /// The king or the rook has moved, so the right to castle on that side
+    /// is gone
+    NoCastlingRight,
+    /// The king would pass over or castle onto `tile`, which is attacked by
+    /// `attackers`
+    CastlingThroughCheck { tile: Position, attackers: Bitboard },
+    /// A pawn can not be promoted to a king or a pawn
+    InvalidPromotion,
 }
 
 impl StdError for Error {
@@ -35,24 +145,93 @@ impl fmt::Display for Error {
             Self::OtherPlayersTurn => write!(f, "Other players turn"),
             Self::NoPieceToMove => write!(f, "No piece to move"),
             Self::IllegalMove => write!(f, "Illegal move"),
+            Self::AmbiguousMove => write!(f, "Ambiguous move"),
             Self::UnknwonPiece(c) => write!(f, "Unknown piece {}", c),
             Self::ParsingError => write!(f, "Parsing error"),
-            Self::FenError(err) => write!(f, "Fen parsing error at {} part", err),
+            Self::FenError(err) => write!(f, "Fen parsing error: {}", err),
+            Self::PgnError(err) => write!(f, "Pgn parsing error: {}", err),
             Self::InvalidGameState => write!(f, "Invalid game state"),
             Self::RequiresPromotion => write!(f, "Move requires specifying promoted piece kind"),
+            Self::NoDrawToClaim => write!(f, "There is no draw to claim"),
+            Self::NoDrawOffer => write!(f, "No draw has been offered"),
+            Self::GameOver => write!(f, "The game is over"),
         }
     }
 }
 
 impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self {
+            Self::Pieces { .. } => "pieces",
+            Self::NextToMove { .. } => "next to move",
+            Self::Castling { .. } => "castling",
+            Self::EnPassant { .. } => "en passant",
+            Self::HalfmoveCounter { .. } => "halfmove counter",
+            Self::MoveNumber { .. } => "move number",
+            Self::TrailingInput { .. } => "end",
+        };
+        write!(
+            f,
+            "{} at character {} ({} part)",
+            self.reason(),
+            self.offset(),
+            field
+        )
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at ply {} ({:?})", self.reason, self.ply, self.token)
+    }
+}
+
+impl fmt::Display for PgnErrorReason {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Pieces => write!(f, "pieces"),
-            Self::NextToMove => write!(f, "next to move"),
-            Self::Castling => write!(f, "castling"),
-            Self::EnPassant => write!(f, "en passant"),
-            Self::HalfmoveCounter => write!(f, "halfmove counter"),
-            Self::MoveNumber => write!(f, "move number"),
+            Self::MalformedTag => write!(f, "malformed tag pair"),
+            Self::InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            Self::UnterminatedComment => write!(f, "unterminated comment"),
+            Self::UnterminatedVariation => write!(f, "unterminated variation"),
+            Self::UnexpectedToken => write!(f, "unexpected token"),
+            Self::Move(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Writes the tiles in `tiles` separated by commas.
+fn write_tiles(f: &mut fmt::Formatter<'_>, tiles: Bitboard) -> fmt::Result {
+    for (i, tile) in tiles.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", tile)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Unreachable => write!(f, "the piece can not move like that"),
+            Self::OwnPiece => write!(f, "the tile is occupied by an own piece"),
+            Self::Blocked { by } => write!(f, "the way is blocked at {}", by),
+            Self::NothingToCapture => write!(f, "a pawn can only move diagonally to capture"),
+            Self::Pinned { pinner } => write!(f, "the piece is pinned by {}", pinner),
+            Self::InCheck { checkers } => {
+                write!(f, "the king is in check by ")?;
+                write_tiles(f, checkers)
+            }
+            Self::IntoCheck { attackers } => {
+                write!(f, "the king would be in check by ")?;
+                write_tiles(f, attackers)
+            }
+            Self::NoCastlingRight => write!(f, "the right to castle on that side is gone"),
+            Self::CastlingThroughCheck { tile, attackers } => {
+                write!(f, "the king can not castle through {}, attacked by ", tile)?;
+                write_tiles(f, attackers)
+            }
+            Self::InvalidPromotion => write!(f, "a pawn can not be promoted to that"),
         }
     }
 }
@@ -0,0 +1,364 @@
+use crate::{bitboard, piece::Kind, Bitboard, Board, Color, Piece, Position};
+
+/// A pair of scores for the middlegame and endgame, which are blended
+/// according to how much material is left on the board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Score {
+    mg: i32,
+    eg: i32,
+}
+
+impl Score {
+    const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+}
+
+impl std::ops::AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        self.mg += rhs.mg;
+        self.eg += rhs.eg;
+    }
+}
+
+impl std::ops::SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.mg -= rhs.mg;
+        self.eg -= rhs.eg;
+    }
+}
+
+impl std::ops::Mul<i32> for Score {
+    type Output = Self;
+    fn mul(self, rhs: i32) -> Self {
+        Self::new(self.mg * rhs, self.eg * rhs)
+    }
+}
+
+/// How much each kind of piece contributes to the game phase. With all
+/// pieces on the board the phase is `MAX_PHASE`, i.e. pure middlegame.
+const PHASE: [i32; 6] = [0, 2, 1, 1, 4, 0];
+const MAX_PHASE: i32 = 24;
+
+/// Bonus per square a piece can move to, indexed by `Kind`.
+const MOBILITY: [Score; 6] = [
+    Score::new(0, 0),
+    Score::new(2, 4),
+    Score::new(4, 4),
+    Score::new(5, 5),
+    Score::new(1, 2),
+    Score::new(0, 0),
+];
+
+const DOUBLED_PAWN: Score = Score::new(-10, -25);
+const ISOLATED_PAWN: Score = Score::new(-12, -18);
+/// Bonus for a passed pawn, indexed by how many ranks it has advanced.
+const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(10, 20),
+    Score::new(15, 35),
+    Score::new(25, 60),
+    Score::new(40, 100),
+    Score::new(60, 150),
+    Score::new(0, 0),
+];
+/// Bonus per pawn right in front of the king
+const PAWN_SHIELD: i32 = 12;
+/// Penalty per attack by an enemy piece on the tiles around the king
+const KING_ZONE_ATTACK: i32 = 8;
+
+/// Piece-square tables, indexed by `Kind` and then by `Position::index` as
+/// seen from white's side, i.e. with the eighth rank first.
+#[rustfmt::skip]
+const PST_MG: [[i32; 64]; 6] = [
+    // Pawn
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         98, 134,  61,  95,  68, 126,  34, -11,
+         -6,   7,  26,  31,  65,  56,  25, -20,
+        -14,  13,   6,  21,  23,  12,  17, -23,
+        -27,  -2,  -5,  12,  17,   6,  10, -25,
+        -26,  -4,  -4, -10,   3,   3,  33, -12,
+        -35,  -1, -20, -23, -15,  24,  38, -22,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // Rook
+    [
+         32,  42,  32,  51,  63,   9,  31,  43,
+         27,  32,  58,  62,  80,  67,  26,  44,
+         -5,  19,  26,  36,  17,  45,  61,  16,
+        -24, -11,   7,  26,  24,  35,  -8, -20,
+        -36, -26, -12,  -1,   9,  -7,   6, -23,
+        -45, -25, -16, -17,   3,   0,  -5, -33,
+        -44, -16, -20,  -9,  -1,  11,  -6, -71,
+        -19, -13,   1,  17,  16,   7, -37, -26,
+    ],
+    // Knight
+    [
+       -167, -89, -34, -49,  61, -97, -15,-107,
+        -73, -41,  72,  36,  23,  62,   7, -17,
+        -47,  60,  37,  65,  84, 129,  73,  44,
+         -9,  17,  19,  53,  37,  69,  18,  22,
+        -13,   4,  16,  13,  28,  19,  21,  -8,
+        -23,  -9,  12,  10,  19,  17,  25, -16,
+        -29, -53, -12,  -3,  -1,  18, -14, -19,
+       -105, -21, -58, -33, -17, -28, -19, -23,
+    ],
+    // Bishop
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    // Queen
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    // King
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+#[rustfmt::skip]
+const PST_EG: [[i32; 64]; 6] = [
+    // Pawn
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // Rook
+    [
+         13,  10,  18,  15,  12,  12,   8,   5,
+         11,  13,  13,  11,  -3,   3,   8,   3,
+          7,   7,   7,   5,   4,  -3,  -5,  -3,
+          4,   3,  13,   1,   2,   1,  -1,   2,
+          3,   5,   8,   4,  -5,  -6,  -8, -11,
+         -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+         -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+         -9,   2,   3,  -1,  -5, -13,   4, -20,
+    ],
+    // Knight
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    // Bishop
+    [
+        -14, -21, -11,  -8,  -7,  -9, -17, -24,
+         -8,  -4,   7, -12,  -3, -13,  -4, -14,
+          2,  -8,   0,  -1,  -2,   6,   0,   4,
+         -3,   9,  12,   9,  14,  10,   3,   2,
+         -6,   3,  13,  19,   7,  10,  -3,  -9,
+        -12,  -3,   8,  10,  13,   3,  -7, -15,
+        -14, -18,  -7,  -1,   4,  -9, -15, -27,
+        -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+    ],
+    // Queen
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    // King
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+/// Index into the piece-square tables for a piece of color `color` at `pos`.
+fn relative_index(pos: Position, color: Color) -> usize {
+    match color {
+        Color::White => pos.index() as usize,
+        // Mirror the rank
+        Color::Black => pos.index() as usize ^ 56,
+    }
+}
+
+/// How many ranks a pawn of color `color` at `pos` has advanced.
+fn advancement(pos: Position, color: Color) -> usize {
+    match color {
+        Color::White => 7 - pos.rank() as usize,
+        Color::Black => pos.rank() as usize,
+    }
+}
+
+/// All tiles on the ranks in front of `pos` as seen by `color`.
+fn ranks_ahead(pos: Position, color: Color) -> Bitboard {
+    let below = |rank: u8| Bitboard(1u64.checked_shl(rank as u32 * 8).map_or(!0, |b| b - 1));
+    match color {
+        Color::White => below(pos.rank()),
+        Color::Black => !below(pos.rank() + 1),
+    }
+}
+
+/// The files next to `file`.
+fn adjacent_files(file: u8) -> Bitboard {
+    let mut files = Bitboard::EMPTY;
+    if file > 0 {
+        files |= Bitboard::file(file - 1);
+    }
+    if file < 7 {
+        files |= Bitboard::file(file + 1);
+    }
+    files
+}
+
+impl Board {
+    /// Returns the static evaluation of the position in centipawns, from the
+    /// perspective of the player to move. Positive values mean the player to
+    /// move is better off.
+    ///
+    /// The evaluation consists of material, piece-square tables, mobility,
+    /// pawn structure and king safety, blended between middlegame and
+    /// endgame weights depending on the material left.
+    pub fn evaluate(&self) -> i32 {
+        let mut score = self.evaluate_color(Color::White);
+        score -= self.evaluate_color(Color::Black);
+
+        let phase = [Kind::Rook, Kind::Knight, Kind::Bishop, Kind::Queen]
+            .iter()
+            .map(|&kind| self.by_kind(kind).count() as i32 * PHASE[kind as usize])
+            .sum::<i32>()
+            .min(MAX_PHASE);
+        let blended = (score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE;
+
+        match self.next_to_move() {
+            Color::White => blended,
+            Color::Black => -blended,
+        }
+    }
+    /// The sum of all terms for `color`, from `color`'s perspective.
+    fn evaluate_color(&self, color: Color) -> Score {
+        let own = self.by_color(color);
+        let occupied = self.occupied();
+        let mut score = Score::default();
+
+        for pos in own {
+            let kind = self[pos].unwrap().kind;
+            let i = relative_index(pos, color);
+            score += Score::new(
+                kind.value() + PST_MG[kind as usize][i],
+                kind.value() + PST_EG[kind as usize][i],
+            );
+            let attacks = match kind {
+                Kind::Knight => bitboard::knight_attacks(pos),
+                Kind::Bishop => bitboard::bishop_attacks(pos, occupied),
+                Kind::Rook => bitboard::rook_attacks(pos, occupied),
+                Kind::Queen => bitboard::queen_attacks(pos, occupied),
+                Kind::Pawn | Kind::King => continue,
+            };
+            score += MOBILITY[kind as usize] * (attacks & !own).count() as i32;
+        }
+
+        score += self.pawn_structure(color);
+        score += Score::new(self.king_safety(color), 0);
+        score
+    }
+    fn pawn_structure(&self, color: Color) -> Score {
+        let pawns = self.by_piece(Piece::new(color, Kind::Pawn));
+        let enemy_pawns = self.by_piece(Piece::new(color.other(), Kind::Pawn));
+        let mut score = Score::default();
+
+        for file in 0..8 {
+            let count = (pawns & Bitboard::file(file)).count() as i32;
+            if count > 1 {
+                score += DOUBLED_PAWN * (count - 1);
+            }
+            if count > 0 && (pawns & adjacent_files(file)).is_empty() {
+                score += ISOLATED_PAWN * count;
+            }
+        }
+        for pos in pawns {
+            let files = Bitboard::file(pos.file()) | adjacent_files(pos.file());
+            let own_ahead = pawns & Bitboard::file(pos.file()) & ranks_ahead(pos, color);
+            // The frontmost of doubled pawns is the only one counted as passed
+            if (enemy_pawns & files & ranks_ahead(pos, color)).is_empty() && own_ahead.is_empty() {
+                score += PASSED_PAWN[advancement(pos, color)];
+            }
+        }
+        score
+    }
+    /// Middlegame bonus for pawns sheltering the king, minus a penalty for
+    /// enemy pieces attacking the tiles around it.
+    fn king_safety(&self, color: Color) -> i32 {
+        let king = match self.by_piece(Piece::new(color, Kind::King)).first() {
+            Some(king) => king,
+            None => return 0,
+        };
+        let pawns = self.by_piece(Piece::new(color, Kind::Pawn));
+        let zone = bitboard::king_attacks(king);
+        let front_rank = |steps: i8| {
+            let rank = king.rank() as i8 + color.forwards() * steps;
+            if (0..8).contains(&rank) {
+                Bitboard::rank(rank as u8)
+            } else {
+                Bitboard::EMPTY
+            }
+        };
+        let files = Bitboard::file(king.file()) | adjacent_files(king.file());
+        let shield = pawns & files & (front_rank(1) | front_rank(2));
+
+        let occupied = self.occupied();
+        let attacks = self
+            .by_color(color.other())
+            .into_iter()
+            .map(|pos| {
+                let attacks = match self[pos].unwrap().kind {
+                    Kind::Knight => bitboard::knight_attacks(pos),
+                    Kind::Bishop => bitboard::bishop_attacks(pos, occupied),
+                    Kind::Rook => bitboard::rook_attacks(pos, occupied),
+                    Kind::Queen => bitboard::queen_attacks(pos, occupied),
+                    Kind::Pawn | Kind::King => Bitboard::EMPTY,
+                };
+                (attacks & zone).count() as i32
+            })
+            .sum::<i32>();
+
+        shield.count() as i32 * PAWN_SHIELD - attacks * KING_ZONE_ATTACK
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{piece, Board, Color, Error, Move, Piece, Position};
+use std::fmt;
+
+use crate::{
+    board::Undo, clock::Clock, error::IllegalMoveReason, piece, Board, Color, Error, Move, Pgn,
+};
 
 /// # Example use:
 /// ```rust
@@ -13,11 +17,11 @@ use crate::{piece, Board, Color, Error, Move, Piece, Position};
 ///         move_.promotion = Some(get_promotion());
 ///     }
 ///     match game.make_move(move_) {
-///         Ok(GameState::Ongoing) => {}
-///         Ok(s) => {
-///             println!("{:?}", s);
+///         Ok(GameState::Over(outcome)) => {
+///             println!("{}", outcome);
 ///             break;
 ///         }
+///         Ok(_) => {}
 ///         Err(err) => {
 ///             println!("{}", err);
 ///             break;
@@ -28,22 +32,183 @@ use crate::{piece, Board, Color, Error, Move, Piece, Position};
 #[derive(Debug, Clone)]
 pub struct Game {
     board: Board,
+    history: Vec<Move>,
+    undo_stack: Vec<Undo>,
+    /// Keys of all positions that have occurred, including the initial and
+    /// current one. Always one longer than `history`.
+    position_keys: Vec<u64>,
+    /// Set once the game is over. Kept since not every way a game can end
+    /// can be seen on the board, e.g. running out of time.
+    outcome: Option<Outcome>,
+    /// The player who has offered a draw that the opponent has not yet
+    /// responded to
+    draw_offer: Option<Color>,
+    clock: Option<Clock>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameState {
     Ongoing,
-    Checkmate { winner: Color },
-    Draw,
+    /// The game goes on, but the player to move may end it in a draw with
+    /// `Game::claim_draw`
+    DrawClaimable(DrawReason),
+    Over(Outcome),
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Decisive { winner: Color, reason: WinReason },
+    Draw(DrawReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinReason {
+    Checkmate,
+    Resignation,
+    /// The loser ran out of time
+    Timeout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    /// The same position occurred three times. Must be claimed.
+    ThreefoldRepetition,
+    /// The same position occurred five times
+    FivefoldRepetition,
+    /// Fifty moves by each player without a capture or pawn move. Must be
+    /// claimed.
+    FiftyMoveRule,
+    /// Seventy-five moves by each player without a capture or pawn move
+    SeventyFiveMoveRule,
+    /// Neither player can ever checkmate the other
+    InsufficientMaterial,
+    Agreement,
+    /// A player ran out of time, but the opponent could never have
+    /// checkmated them
+    TimeoutVsInsufficientMaterial,
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<Color> {
+        match *self {
+            Self::Decisive { winner, .. } => Some(winner),
+            Self::Draw(_) => None,
+        }
+    }
+    /// The result as written in PGN, i.e. "1-0", "0-1" or "1/2-1/2".
+    pub fn result(&self) -> &'static str {
+        match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+    /// The value of the PGN "Termination" tag for the outcome.
+    pub fn termination(&self) -> &'static str {
+        match self {
+            Self::Decisive {
+                reason: WinReason::Timeout,
+                ..
+            }
+            | Self::Draw(DrawReason::TimeoutVsInsufficientMaterial) => "time forfeit",
+            _ => "normal",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decisive { winner, reason } => write!(f, "{:?} wins by {}", winner, reason),
+            Self::Draw(reason) => write!(f, "Draw by {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for WinReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Checkmate => write!(f, "checkmate"),
+            Self::Resignation => write!(f, "resignation"),
+            Self::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stalemate => write!(f, "stalemate"),
+            Self::ThreefoldRepetition => write!(f, "threefold repetition"),
+            Self::FivefoldRepetition => write!(f, "fivefold repetition"),
+            Self::FiftyMoveRule => write!(f, "the fifty-move rule"),
+            Self::SeventyFiveMoveRule => write!(f, "the seventy-five-move rule"),
+            Self::InsufficientMaterial => write!(f, "insufficient material"),
+            Self::Agreement => write!(f, "agreement"),
+            Self::TimeoutVsInsufficientMaterial => {
+                write!(f, "timeout against insufficient material")
+            }
+        }
+    }
 }
 
 impl Game {
     pub fn new(board: Board) -> Self {
-        Self { board }
+        Self {
+            position_keys: vec![board.zobrist()],
+            board,
+            history: Vec::new(),
+            undo_stack: Vec::new(),
+            outcome: None,
+            draw_offer: None,
+            clock: None,
+        }
     }
     pub fn board(&self) -> &Board {
         &self.board
     }
+    /// Returns the board as it was when the game was created.
+    pub fn initial_board(&self) -> Board {
+        let mut board = self.board.clone();
+        for (&move_, &undo) in self.history.iter().zip(&self.undo_stack).rev() {
+            board.unmake_move(move_, undo);
+        }
+        board
+    }
+    /// Returns the game in Portable Game Notation, with unknown values in the
+    /// seven tag roster left as "?". Use `Pgn` directly to set tags.
+    pub fn to_pgn(&self) -> String {
+        Pgn {
+            tags: Vec::new(),
+            game: self.clone(),
+        }
+        .to_string()
+    }
+    /// All moves made since the game was created, in the order they were made.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+    /// Keys of all positions that have occurred, starting with the initial
+    /// one and ending with the current one.
+    pub(crate) fn position_keys(&self) -> &[u64] {
+        &self.position_keys
+    }
+    /// Returns how many times the current position has occurred, counting
+    /// the current occurrence.
+    pub fn repetition_count(&self) -> usize {
+        let current = self.position_keys.last().unwrap();
+        // Positions can only repeat since the last capture or pawn move, and
+        // only with the same player to move
+        self.position_keys
+            .iter()
+            .rev()
+            .take(self.board.halfmove_counter as usize + 1)
+            .step_by(2)
+            .filter(|&key| key == current)
+            .count()
+    }
     /// Indicates if the is missing the additional `promotion` field when it's
     /// needed. If it's not needed for the move, or if it's already set, false
     /// is returned.
@@ -64,156 +229,250 @@ impl Game {
         };
         piece.kind == piece::Kind::Pawn && move_.to.rank() == piece.color.other().home_rank()
     }
-    pub fn all_legal_moves<'s>(&'s self) -> impl Iterator<Item = Move> + 's {
-        (0..8)
-            .map(move |rank| {
-                (0..8).map(move |file| {
-                    let from = Position::new_unchecked(file, rank);
-                    (from, self.board()[from])
-                })
-            })
-            .flatten()
-            .flat_map(move |(from, piece)| match piece {
-                Some(piece) if piece.color == self.board().next_to_move() => Some((from, piece)),
-                _ => None,
-            })
-            .map(move |(from, piece)| {
-                piece.moves(self.board(), from).map(move |to| Move {
-                    from,
-                    to,
-                    promotion: None,
-                })
-            })
-            .flatten()
+    pub fn all_legal_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.board.legal_moves()
+    }
+    /// Parses a move in standard algebraic notation, e.g. "Nbd7", against the
+    /// current position. See `Move::from_san`.
+    pub fn parse_san(&self, s: &str) -> Result<Move, Error> {
+        Move::from_san(s, &self.board)
     }
+    /// Makes a move for the player to move. Castling is written as the king
+    /// capturing its own rook, but in standard chess, the king moving two
+    /// files is accepted as well. If the game has a clock and the player has
+    /// run out of time, the move is not made and the game ends by timeout
+    /// instead.
+    ///
+    /// The move is checked completely before anything is changed, so when an
+    /// error is returned, the game is left exactly as it was.
     pub fn make_move<M>(&mut self, move_: M) -> Result<GameState, Error>
     where
         M: Into<Move>,
     {
-        let move_ = move_.into();
-        if let Some(piece) = self.board[move_.from] {
-            if piece.color != self.board.next_to_move() {
-                return Err(Error::OtherPlayersTurn);
-            }
-            if !piece.moves(self.board(), move_.from).any(|p| p == move_.to) {
-                return Err(Error::IllegalMove);
-            }
-        } else {
-            return Err(Error::NoPieceToMove);
+        if self.outcome.is_some() {
+            return Err(Error::GameOver);
+        }
+        if let state @ GameState::Over(_) = self.check_flag() {
+            return Ok(state);
         }
-        self.make_move_unchecked(move_)
+        let move_ = self.validate_move(move_.into())?;
+        Ok(self.make_move_unchecked(move_))
     }
-    /// Make the move without checking if the piece at `move_.from` exists or
-    /// can move to `move_.to` legally.
-    ///
-    /// `checks` signifies whether the opponent will be in check after the move.
-    fn make_move_unchecked<M>(&mut self, move_: M) -> Result<GameState, Error>
+    /// Returns why `move_` is illegal, if `make_move` would refuse it with
+    /// `Error::IllegalMove`. See `Board::explain_illegal`.
+    pub fn explain_illegal<M>(&self, move_: M) -> Option<IllegalMoveReason>
     where
         M: Into<Move>,
     {
-        let move_: Move = move_.into();
-        let piece = self.board[move_.from].unwrap();
-        let current_color = self.board.next_to_move();
-        let mut captured = self.board[move_.to];
-
-        self.board[move_.to] = self.board[move_.from].take();
+        if self.outcome.is_some() {
+            return None;
+        }
+        self.board.explain_illegal(move_.into())
+    }
+    /// Takes back the last move made, returning it. Returns `None` if no
+    /// moves have been made since the game was created.
+    ///
+    /// Time on the clock is not given back, but the clock is switched to the
+    /// player to move.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let move_ = self.history.pop()?;
+        let undo = self.undo_stack.pop().unwrap();
+        self.position_keys.pop();
+        self.outcome = None;
+        self.draw_offer = None;
+        self.board.unmake_move(move_, undo);
+        if let Some(clock) = &mut self.clock {
+            clock.start(self.board.next_to_move());
+        }
+        Some(move_)
+    }
+    /// Checks that `move_` is legal for the player to move, returning it in
+    /// the form it is made in: castling as the king capturing its rook, and
+    /// with `promotion` set only if a pawn reaches the last rank.
+    fn validate_move(&self, move_: Move) -> Result<Move, Error> {
+        let mut move_ = self.board.normalize_castling(move_);
+        let piece = self.board[move_.from].ok_or(Error::NoPieceToMove)?;
+        if piece.color != self.board.next_to_move() {
+            return Err(Error::OtherPlayersTurn);
+        }
+        if !piece.moves(self.board(), move_.from).any(|p| p == move_.to) {
+            return Err(Error::IllegalMove);
+        }
 
-        // Handle promotion
-        if piece.kind == piece::Kind::Pawn && (move_.to.rank() == 7 || move_.to.rank() == 0) {
-            let promoted_kind = match move_.promotion {
-                None | Some(piece::Kind::King) | Some(piece::Kind::Pawn) => {
-                    return Err(Error::RequiresPromotion)
-                }
-                Some(kind) => kind,
-            };
-            let promoted = Piece::new(current_color, promoted_kind);
-            self.board[move_.to] = Some(promoted);
+        if piece.kind == piece::Kind::Pawn && move_.to.rank() == piece.color.other().home_rank() {
+            match move_.promotion {
+                None => return Err(Error::RequiresPromotion),
+                Some(kind) if !kind.is_valid_promotion() => return Err(Error::IllegalMove),
+                Some(_) => {}
+            }
+        } else {
+            move_.promotion = None;
         }
+        Ok(move_)
+    }
+    /// Makes a move that has been checked with `validate_move`.
+    fn make_move_unchecked(&mut self, move_: Move) -> GameState {
+        let color = self.board.next_to_move();
 
-        // Handle castling
-        let delta_file = move_.to.file() as i8 - move_.from.file() as i8;
-        if piece.kind == piece::Kind::King && delta_file.abs() == 2 {
-            let rook_pos =
-                Position::new_unchecked(if delta_file > 0 { 7 } else { 0 }, move_.to.rank());
-            let rook_dst_file = move_.to.file() as i8 + -delta_file / 2;
-            let rook_dst = Position::new_unchecked(rook_dst_file as u8, move_.to.rank());
-            self.board[rook_dst] = self.board[rook_pos].take();
+        // Moving instead of answering a draw offer declines it
+        if self.draw_offer == Some(color.other()) {
+            self.draw_offer = None;
         }
 
-        // Handle castling marking
-        match (piece.kind, move_.from.file()) {
-            (piece::Kind::King, _) => {
-                self.board.cannot_castle_kingside(current_color);
-                self.board.cannot_castle_queenside(current_color);
+        let undo = self.board.make_move_unchecked(move_);
+        self.history.push(move_);
+        self.undo_stack.push(undo);
+        self.position_keys.push(self.board.zobrist());
+
+        let state = self.position_state();
+        if let GameState::Over(outcome) = state {
+            self.outcome = Some(outcome);
+            self.draw_offer = None;
+            if let Some(clock) = &mut self.clock {
+                clock.stop();
             }
-            (piece::Kind::Rook, 0) => self.board.cannot_castle_queenside(current_color),
-            (piece::Kind::Rook, 7) => self.board.cannot_castle_kingside(current_color),
-            _ => {}
+        } else if let Some(clock) = &mut self.clock {
+            clock.press();
         }
-        if move_.to == Position::new_unchecked(0, current_color.other().home_rank()) {
-            self.board.cannot_castle_queenside(current_color.other());
-        }
-        if move_.to == Position::new_unchecked(7, current_color.other().home_rank()) {
-            self.board.cannot_castle_kingside(current_color.other());
+        state
+    }
+    /// Plays the game on `clock`, starting it for the player to move. From
+    /// then on, making a move presses the clock.
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        if self.outcome.is_none() {
+            clock.start(self.board.next_to_move());
         }
-
-        // Handle en passant capture
-        if piece.kind == piece::Kind::Pawn && Some(move_.to) == self.board.en_passant_square() {
-            let target_rank = move_.to.rank() as i8 + current_color.backwards();
-            let target = Position::new_unchecked(move_.to.file(), target_rank as u8);
-            captured = self.board[target].take();
+        self.clock = Some(clock);
+    }
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+    /// Ends the game by timeout if a player has run out of time on the
+    /// clock, and returns the state of the game. Should be called
+    /// regularly while waiting for a move, since a flag fall is otherwise
+    /// only noticed when the player tries to move.
+    pub fn check_flag(&mut self) -> GameState {
+        let flagged = self.clock.as_ref().and_then(Clock::flagged);
+        if let (None, Some(color)) = (self.outcome, flagged) {
+            // Can't fail since the game isn't over
+            let _ = self.timeout(color);
         }
-
-        // Handle en passant marking
-        let delta_rank = move_.to.rank() as i8 - move_.from.rank() as i8;
-        if piece.kind == piece::Kind::Pawn && delta_rank.abs() == 2 {
-            let eps_rank = move_.to.rank() as i8 + current_color.backwards();
-            self.board
-                .set_en_passant_square(Some(Position::new_unchecked(
-                    move_.to.file(),
-                    eps_rank as u8,
-                )));
+        self.state()
+    }
+    /// Returns how the game ended, or `None` if it's not over.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+    /// Ends the game by `color` running out of time. That is a loss, unless
+    /// the opponent could never checkmate `color` anyway, which makes it a
+    /// draw.
+    pub fn timeout(&mut self, color: Color) -> Result<GameState, Error> {
+        let outcome = if self.board.has_insufficient_material(color.other()) {
+            Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
         } else {
-            self.board.set_en_passant_square(None);
+            Outcome::Decisive {
+                winner: color.other(),
+                reason: WinReason::Timeout,
+            }
+        };
+        self.end(outcome)
+    }
+    /// Ends the game by `color` resigning.
+    pub fn resign(&mut self, color: Color) -> Result<GameState, Error> {
+        self.end(Outcome::Decisive {
+            winner: color.other(),
+            reason: WinReason::Resignation,
+        })
+    }
+    /// Lets `color` offer the opponent a draw. The offer stands until the
+    /// opponent accepts it, declines it or makes a move.
+    pub fn offer_draw(&mut self, color: Color) -> Result<(), Error> {
+        if self.outcome.is_some() {
+            return Err(Error::GameOver);
         }
-
-        self.board.switch_next_to_move();
-        if captured.is_some() || piece.kind == piece::Kind::Pawn {
-            self.board.reset_halfmove_counter();
+        self.draw_offer = Some(color);
+        Ok(())
+    }
+    /// The player with a pending draw offer, if any.
+    pub fn draw_offer(&self) -> Option<Color> {
+        self.draw_offer
+    }
+    /// Accepts the opponent's pending draw offer on behalf of `color`,
+    /// ending the game in a draw. Players can't accept their own offers.
+    pub fn accept_draw(&mut self, color: Color) -> Result<GameState, Error> {
+        if self.outcome.is_none() && self.draw_offer != Some(color.other()) {
+            return Err(Error::NoDrawOffer);
         }
-
-        let mut has_moves = false;
-        'outer: for rank in 0..8 {
-            for file in 0..8 {
-                let pos = Position::new_unchecked(file, rank);
-                if let Some(piece) = self.board[pos] {
-                    if piece.color == self.board.next_to_move()
-                        && piece.moves(&self.board, pos).count() > 0
-                    {
-                        has_moves = true;
-                        break 'outer;
-                    }
-                }
-            }
+        self.end(Outcome::Draw(DrawReason::Agreement))
+    }
+    /// Declines the opponent's pending draw offer on behalf of `color`.
+    pub fn decline_draw(&mut self, color: Color) -> Result<(), Error> {
+        if self.draw_offer != Some(color.other()) {
+            return Err(Error::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+    fn end(&mut self, outcome: Outcome) -> Result<GameState, Error> {
+        if self.outcome.is_some() {
+            return Err(Error::GameOver);
+        }
+        self.outcome = Some(outcome);
+        self.draw_offer = None;
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
         }
+        Ok(self.state())
+    }
+    /// Ends the game in a draw, if the current position allows the player to
+    /// move to claim one, i.e. if the state is `GameState::DrawClaimable`.
+    /// Otherwise `Err(Error::NoDrawToClaim)` is returned.
+    pub fn claim_draw(&mut self) -> Result<GameState, Error> {
+        match self.state() {
+            GameState::DrawClaimable(reason) => self.end(Outcome::Draw(reason)),
+            GameState::Over(_) => Err(Error::GameOver),
+            GameState::Ongoing => Err(Error::NoDrawToClaim),
+        }
+    }
+    /// Returns the state of the game.
+    ///
+    /// The game is drawn automatically by stalemate, a dead position, the
+    /// same position occurring five times or 75 moves by each player without
+    /// a capture or pawn move. After 50 such moves, or when the same position
+    /// has occurred three times, a draw can be claimed.
+    pub fn state(&self) -> GameState {
+        match self.outcome {
+            Some(outcome) => GameState::Over(outcome),
+            None => self.position_state(),
+        }
+    }
+    /// The state of the game as far as can be told from the position.
+    fn position_state(&self) -> GameState {
+        let has_moves = self.board.has_legal_moves();
+        let halfmoves = self.board.halfmove_counter;
+        let repetitions = self.repetition_count();
+        let draw = |reason| GameState::Over(Outcome::Draw(reason));
         if !has_moves {
-            if piece::util::threatened_at(
-                self.board.get_king_position(self.board.next_to_move()),
-                &[],
-                &[],
-                self.board.next_to_move(),
-                &self.board,
-            ) {
-                Ok(GameState::Checkmate {
+            if self.board.is_check() {
+                GameState::Over(Outcome::Decisive {
                     winner: self.board.next_to_move().other(),
+                    reason: WinReason::Checkmate,
                 })
             } else {
-                Ok(GameState::Draw)
+                draw(DrawReason::Stalemate)
             }
-        } else if self.board.halfmove_counter == 50 {
-            Ok(GameState::Draw)
+        } else if self.board.is_dead_position() {
+            draw(DrawReason::InsufficientMaterial)
+        } else if repetitions >= 5 {
+            draw(DrawReason::FivefoldRepetition)
+        } else if halfmoves >= 150 {
+            draw(DrawReason::SeventyFiveMoveRule)
+        } else if repetitions >= 3 {
+            GameState::DrawClaimable(DrawReason::ThreefoldRepetition)
+        } else if halfmoves >= 100 {
+            GameState::DrawClaimable(DrawReason::FiftyMoveRule)
         } else {
-            Ok(GameState::Ongoing)
+            GameState::Ongoing
         }
     }
 }
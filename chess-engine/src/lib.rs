@@ -2,16 +2,28 @@
 
 #![deny(warnings)]
 
+mod bitboard;
 mod board;
-mod error;
+pub mod clock;
+pub mod error;
+mod eval;
 mod game;
+mod perft;
+mod pgn;
 pub mod piece;
+mod san;
+mod search;
+pub mod tt;
 mod util;
 
-pub use board::Board;
+pub use bitboard::Bitboard;
+pub use board::{AttackMap, Board, Pin, Undo};
 pub use error::Error;
-pub use game::{Game, GameState};
+pub use game::{DrawReason, Game, GameState, Outcome, WinReason};
+pub use pgn::Pgn;
 pub use piece::Piece;
+pub use search::{Limits, Score, SearchResult, Searcher};
+pub use tt::TranspositionTable;
 pub use util::{Color, Move, Position};
 
 #[cfg(test)]
@@ -0,0 +1,82 @@
+use crate::{
+    tt::{PerftEntry, TranspositionTable},
+    Board, Move,
+};
+
+impl Board {
+    /// Counts the leaf nodes of the tree of all legal move sequences `depth`
+    /// plies long, counting each promotion kind as a separate move. Comparing
+    /// the counts with known ones is the standard way to find bugs in move
+    /// generation.
+    pub fn perft(&self, depth: u8) -> u64 {
+        perft(&mut self.clone(), depth, None)
+    }
+    /// Same as `perft`, but remembers the counts of subtrees in `table`, so
+    /// that positions reached through transpositions are only counted once.
+    pub fn perft_cached(&self, depth: u8, table: &mut TranspositionTable<PerftEntry>) -> u64 {
+        perft(&mut self.clone(), depth, Some(table))
+    }
+    /// Same as `perft`, but with the count split up by the first move, which
+    /// helps narrow down where counts differ from another engine's.
+    pub fn divide(&self, depth: u8) -> Vec<(Move, u64)> {
+        divide(&mut self.clone(), depth, None)
+    }
+    /// Same as `divide`, but remembers the counts of subtrees in `table`, as
+    /// `perft_cached` does.
+    pub fn divide_cached(
+        &self,
+        depth: u8,
+        table: &mut TranspositionTable<PerftEntry>,
+    ) -> Vec<(Move, u64)> {
+        divide(&mut self.clone(), depth, Some(table))
+    }
+}
+
+fn divide(
+    board: &mut Board,
+    depth: u8,
+    mut table: Option<&mut TranspositionTable<PerftEntry>>,
+) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let moves = board.legal_moves_with_promotions().collect::<Vec<_>>();
+    moves
+        .into_iter()
+        .map(|move_| {
+            let undo = board.make_move_unchecked(move_);
+            let nodes = perft(board, depth - 1, table.as_deref_mut());
+            board.unmake_move(move_, undo);
+            (move_, nodes)
+        })
+        .collect()
+}
+
+fn perft(
+    board: &mut Board,
+    depth: u8,
+    mut table: Option<&mut TranspositionTable<PerftEntry>>,
+) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if depth == 1 {
+        return board.legal_moves_with_promotions().count() as u64;
+    }
+    let key = board.zobrist();
+    if let Some(nodes) = table.as_deref().and_then(|t| t.nodes(key, depth)) {
+        return nodes;
+    }
+
+    let mut nodes = 0;
+    for move_ in board.legal_moves_with_promotions().collect::<Vec<_>>() {
+        let undo = board.make_move_unchecked(move_);
+        nodes += perft(board, depth - 1, table.as_deref_mut());
+        board.unmake_move(move_, undo);
+    }
+
+    if let Some(table) = table {
+        table.store_nodes(key, depth, nodes);
+    }
+    nodes
+}
@@ -0,0 +1,378 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    error::{PgnError, PgnErrorReason},
+    Board, Color, Error, Game,
+};
+
+/// A game read from Portable Game Notation.
+///
+/// # Example use:
+/// ```rust
+/// # use chess_engine::{Pgn, GameState};
+/// let pgn: Pgn = r#"
+/// [Event "Casual game"]
+/// [White "Fool"]
+/// [Black "Opponent"]
+/// [Result "0-1"]
+///
+/// 1. f3 e5 2. g4 {Blunder} Qh4# 0-1
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// assert_eq!(Some("Fool"), pgn.tag("White"));
+/// assert_eq!(4, pgn.game.history().len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pgn {
+    /// All tag pairs in the order they appeared
+    pub tags: Vec<(String, String)>,
+    /// The game with all moves of the main line made
+    pub game: Game,
+}
+
+impl Pgn {
+    /// Returns the value of the tag named `name`, if present.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+    /// Parses all games in `s`, e.g. the contents of a PGN database file.
+    pub fn parse_all(s: &str) -> Result<Vec<Self>, Error> {
+        let mut parser = Parser { s, pos: 0 };
+        let mut games = Vec::new();
+        loop {
+            parser.skip_whitespace();
+            if parser.rest().is_empty() {
+                break Ok(games);
+            }
+            games.push(parser.game()?);
+        }
+    }
+}
+
+impl FromStr for Pgn {
+    type Err = Error;
+    /// Parses the first game in `s`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser { s, pos: 0 }.game()
+    }
+}
+
+/// The tags every PGN must have, in the order they must appear, along with
+/// their values when unknown.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Maximum length of lines in exported movetext
+const LINE_WIDTH: usize = 80;
+
+impl Pgn {
+    /// The result token for the game. Derived from the outcome if the game
+    /// is over, otherwise taken from the "Result" tag.
+    pub fn result(&self) -> &str {
+        match self.game.outcome() {
+            Some(outcome) => outcome.result(),
+            None => self
+                .tag("Result")
+                .filter(|r| RESULTS.contains(r))
+                .unwrap_or("*"),
+        }
+    }
+}
+
+fn write_tag(f: &mut fmt::Formatter<'_>, name: &str, value: &str) -> fmt::Result {
+    writeln!(
+        f,
+        "[{} \"{}\"]",
+        name,
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+impl fmt::Display for Pgn {
+    /// Writes the game in PGN export format: the seven tag roster followed by
+    /// the remaining tags, and the moves in SAN wrapped at 80 columns. The
+    /// "Termination" tag is written from the outcome if the game is over.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = self.result();
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result,
+                _ => self.tag(name).unwrap_or(default),
+            };
+            write_tag(f, name, value)?;
+        }
+
+        let initial = self.game.initial_board();
+        let custom_start = initial != Board::default();
+        if initial.is_chess960() {
+            write_tag(f, "Variant", "Chess960")?;
+        }
+        if custom_start {
+            write_tag(f, "SetUp", "1")?;
+            write_tag(f, "FEN", &initial.to_fen())?;
+        }
+        let outcome = self.game.outcome();
+        if let Some(outcome) = outcome {
+            write_tag(f, "Termination", outcome.termination())?;
+        }
+        for (name, value) in &self.tags {
+            let generated = SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name)
+                || name == "SetUp"
+                || name == "FEN"
+                || name == "Variant" && initial.is_chess960()
+                || name == "Termination" && outcome.is_some();
+            if !generated {
+                write_tag(f, name, value)?;
+            }
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::new();
+        let mut board = initial;
+        for (i, &move_) in self.game.history().iter().enumerate() {
+            if board.next_to_move() == Color::White {
+                tokens.push(format!("{}.", board.move_number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", board.move_number));
+            }
+            tokens.push(
+                move_
+                    .as_algebraic(&board)
+                    .expect("moves in the history are legal"),
+            );
+            board.make_move_unchecked(move_);
+        }
+        tokens.push(result.to_owned());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > LINE_WIDTH {
+                writeln!(f)?;
+                line_len = 0;
+            }
+            if line_len > 0 {
+                write!(f, " ")?;
+                line_len += 1;
+            }
+            write!(f, "{}", token)?;
+            line_len += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+struct Parser<'s> {
+    s: &'s str,
+    pos: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn rest(&self) -> &'s str {
+        &self.s[self.pos..]
+    }
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+    fn skip_line(&mut self) {
+        while !matches!(self.bump(), Some('\n') | None) {}
+    }
+    fn error(ply: usize, token: &str, reason: PgnErrorReason) -> Error {
+        Error::PgnError(PgnError {
+            ply,
+            token: token.to_owned(),
+            reason,
+        })
+    }
+    /// Reads a symbol, i.e. anything up to the next whitespace or special
+    /// character.
+    fn symbol(&mut self) -> &'s str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !"{}()[];$\"".contains(c))
+        {
+            self.bump();
+        }
+        &self.s[start..self.pos]
+    }
+    /// Parses a tag pair like `[Event "Casual game"]`, starting at the `[`.
+    fn tag(&mut self) -> Result<(String, String), Error> {
+        let start = self.pos;
+        let malformed = |p: &Self| {
+            let token = p.s[start..p.pos].lines().next().unwrap_or("");
+            Self::error(0, token, PgnErrorReason::MalformedTag)
+        };
+        self.bump();
+        self.skip_whitespace();
+        let name = self.symbol().to_owned();
+        self.skip_whitespace();
+        if name.is_empty() || self.bump() != Some('"') {
+            return Err(malformed(self));
+        }
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => match self.bump() {
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    _ => return Err(malformed(self)),
+                },
+                Some('\n') | None => return Err(malformed(self)),
+                Some(c) => value.push(c),
+            }
+        }
+        self.skip_whitespace();
+        if self.bump() != Some(']') {
+            return Err(malformed(self));
+        }
+        Ok((name, value))
+    }
+    /// Parses a single game, consisting of tag pairs followed by movetext
+    /// ending with a result token.
+    fn game(&mut self) -> Result<Pgn, Error> {
+        let mut tags = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('[') => tags.push(self.tag()?),
+                Some('%') => self.skip_line(),
+                _ => break,
+            }
+        }
+
+        let mut board = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::from_fen(fen)
+                .map_err(|err| Self::error(0, fen, PgnErrorReason::InvalidFen(Box::new(err))))?,
+            None => Board::default(),
+        };
+        if tags.iter().any(|(name, value)| {
+            name == "Variant"
+                && ["chess960", "fischerandom"].contains(&value.to_ascii_lowercase().as_str())
+        }) {
+            board.set_chess960(true);
+        }
+        let mut game = Game::new(board);
+        let mut variation_depth = 0;
+
+        loop {
+            self.skip_whitespace();
+            let ply = game.history().len() + 1;
+            let start = self.pos;
+            match self.peek() {
+                None | Some('[') => {
+                    // The result token is required by the standard but often
+                    // left out, so a new game or the end of input also ends
+                    // the current game
+                    if variation_depth > 0 {
+                        return Err(Self::error(ply, "(", PgnErrorReason::UnterminatedVariation));
+                    }
+                    break;
+                }
+                Some('{') => {
+                    match self.rest().find('}') {
+                        Some(end) => self.pos += end + 1,
+                        None => {
+                            let token = self.rest().lines().next().unwrap_or("");
+                            return Err(Self::error(
+                                ply,
+                                token,
+                                PgnErrorReason::UnterminatedComment,
+                            ));
+                        }
+                    }
+                    continue;
+                }
+                Some(';') => {
+                    self.skip_line();
+                    continue;
+                }
+                Some('%') if self.pos == 0 || self.s[..self.pos].ends_with('\n') => {
+                    self.skip_line();
+                    continue;
+                }
+                Some('(') => {
+                    self.bump();
+                    variation_depth += 1;
+                    continue;
+                }
+                Some(')') => {
+                    self.bump();
+                    if variation_depth == 0 {
+                        return Err(Self::error(ply, ")", PgnErrorReason::UnexpectedToken));
+                    }
+                    variation_depth -= 1;
+                    continue;
+                }
+                Some('$') => {
+                    self.bump();
+                    let nag = self.symbol();
+                    if nag.is_empty() || !nag.bytes().all(|b| b.is_ascii_digit()) {
+                        let token = &self.s[start..self.pos];
+                        return Err(Self::error(ply, token, PgnErrorReason::UnexpectedToken));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let token = self.symbol();
+            if token.is_empty() {
+                let token = &self.rest()[..self.peek().map_or(0, char::len_utf8)];
+                return Err(Self::error(ply, token, PgnErrorReason::UnexpectedToken));
+            }
+            if variation_depth > 0 {
+                continue;
+            }
+            if RESULTS.contains(&token) {
+                if !tags.iter().any(|(name, _)| name == "Result") {
+                    tags.push(("Result".to_owned(), token.to_owned()));
+                }
+                break;
+            }
+
+            // Move numbers may be written right next to the move, e.g. "1.e4"
+            let san = token.trim_start_matches(|c: char| c.is_ascii_digit());
+            let san = if san.len() < token.len() && san.starts_with('.') {
+                san.trim_start_matches('.')
+            } else {
+                token
+            };
+            if san.is_empty() || san.bytes().all(|b| b == b'!' || b == b'?') {
+                // Just a move number or a detached annotation
+                continue;
+            }
+
+            let move_ = game
+                .parse_san(san)
+                .map_err(|err| Self::error(ply, token, PgnErrorReason::Move(Box::new(err))))?;
+            game.make_move(move_)
+                .map_err(|err| Self::error(ply, token, PgnErrorReason::Move(Box::new(err))))?;
+        }
+
+        Ok(Pgn { tags, game })
+    }
+}
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::{Board, Color, Error, Position};
+use crate::{bitboard, Bitboard, Board, Color, Error, Position};
 
 mod bishop;
 mod king;
@@ -10,7 +10,7 @@ mod queen;
 mod rook;
 pub mod util;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct Piece {
     pub color: Color,
     pub kind: Kind,
@@ -26,26 +26,16 @@ pub enum Kind {
     King,
 }
 
-pub enum Moves<'b> {
-    Pawn(pawn::Moves<'b>),
-    Rook(rook::Moves<'b>),
-    Knight(knight::Moves<'b>),
-    Bishop(bishop::Moves<'b>),
-    Queen(queen::Moves<'b>),
-    King(king::Moves<'b>),
-}
+/// Iterator over the tiles a piece can legally move to.
+pub struct Moves(bitboard::Iter);
 
-impl<'b> Iterator for Moves<'b> {
+impl Iterator for Moves {
     type Item = Position;
     fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            Self::Pawn(moves) => moves.next(),
-            Self::Rook(moves) => moves.next(),
-            Self::Knight(moves) => moves.next(),
-            Self::Bishop(moves) => moves.next(),
-            Self::Queen(moves) => moves.next(),
-            Self::King(moves) => moves.next(),
-        }
+        self.0.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
 }
 
@@ -88,14 +78,32 @@ impl Piece {
             self.kind.name()
         }
     }
-    pub fn moves<'b>(&self, board: &'b Board, from: Position) -> Moves<'b> {
+    /// Returns all tiles the piece can legally move to if it stands at
+    /// `from` on `board`.
+    pub fn moves(&self, board: &Board, from: Position) -> Moves {
+        Moves(self.targets(board, from).into_iter())
+    }
+    /// Same as `moves`, but as a `Bitboard`.
+    pub fn targets(&self, board: &Board, from: Position) -> Bitboard {
+        match self.kind {
+            Kind::Pawn => pawn::targets(board, from),
+            Kind::Rook => rook::targets(board, from),
+            Kind::Knight => knight::targets(board, from),
+            Kind::Bishop => bishop::targets(board, from),
+            Kind::Queen => queen::targets(board, from),
+            Kind::King => king::targets(board, from),
+        }
+    }
+    /// Returns all tiles the piece attacks if it stands at `from` on `board`,
+    /// whether or not it could legally move there.
+    pub fn attacks(&self, board: &Board, from: Position) -> Bitboard {
         match self.kind {
-            Kind::Pawn => Moves::Pawn(pawn::Moves::new(board, from)),
-            Kind::Rook => Moves::Rook(rook::Moves::new(board, from)),
-            Kind::Knight => Moves::Knight(knight::Moves::new(board, from)),
-            Kind::Bishop => Moves::Bishop(bishop::Moves::new(board, from)),
-            Kind::Queen => Moves::Queen(queen::Moves::new(board, from)),
-            Kind::King => Moves::King(king::Moves::new(board, from)),
+            Kind::Pawn => pawn::attacks(from, self.color),
+            Kind::Rook => rook::attacks(from, board.occupied()),
+            Kind::Knight => knight::attacks(from),
+            Kind::Bishop => bishop::attacks(from, board.occupied()),
+            Kind::Queen => queen::attacks(from, board.occupied()),
+            Kind::King => king::attacks(from),
         }
     }
     pub fn checks(&self, at: Position, board: &Board) -> bool {
@@ -122,6 +130,22 @@ impl Kind {
             _ => Err(Error::ParsingError),
         }
     }
+    /// The conventional material value of the kind of piece in centipawns.
+    /// The king can never be traded, so it has no value.
+    pub fn value(&self) -> i32 {
+        match *self {
+            Self::Pawn => 100,
+            Self::Knight => 320,
+            Self::Bishop => 330,
+            Self::Rook => 500,
+            Self::Queen => 900,
+            Self::King => 0,
+        }
+    }
+    /// Indicates if a pawn can be promoted to this kind of piece.
+    pub fn is_valid_promotion(&self) -> bool {
+        !matches!(self, Self::Pawn | Self::King)
+    }
     pub fn name(&self) -> char {
         match *self {
             Self::Pawn => 'P',
@@ -1,24 +1,15 @@
-use crate::{Board, Color, Position};
+use crate::{bitboard, Bitboard, Board, Color, Position};
 
-use super::util::{self, floating_checks};
+use super::util::{attacks_king, legal_targets};
 
-const DELTAS: &[(i8, i8)] = &[(1, 1), (1, -1), (-1, -1), (-1, 1)];
-
-pub struct Moves<'b>(util::Moves<'b>);
-
-impl<'b> Moves<'b> {
-    pub fn new(board: &'b Board, from: Position) -> Self {
-        Moves(util::Moves::new(board, from, DELTAS))
-    }
+pub fn attacks(from: Position, occupied: Bitboard) -> Bitboard {
+    bitboard::bishop_attacks(from, occupied)
 }
 
-impl<'b> Iterator for Moves<'b> {
-    type Item = Position;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next()
-    }
+pub fn targets(board: &Board, from: Position) -> Bitboard {
+    legal_targets(board, from, attacks(from, board.occupied()))
 }
 
 pub fn checks(at: Position, color: Color, board: &Board) -> bool {
-    floating_checks(DELTAS, at, color, board)
+    attacks_king(attacks(at, board.occupied()), color, board)
 }
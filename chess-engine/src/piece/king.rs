@@ -1,86 +1,68 @@
-use crate::{Board, Color, Position};
+use crate::{bitboard, board, Bitboard, Board, Color, Position};
 
-use super::util::threatened_at;
-use super::Piece;
+pub fn attacks(from: Position) -> Bitboard {
+    bitboard::king_attacks(from)
+}
 
+/// A king can never give check itself
 pub fn checks(_at: Position, _color: Color, _board: &Board) -> bool {
     false
 }
 
-pub struct Moves<'b> {
-    board: &'b Board,
-    from: Position,
-    color: Color,
-    state: u8,
-}
+pub fn targets(board: &Board, from: Position) -> Bitboard {
+    let color = board[from].unwrap().color;
+    let enemies = board.by_color(color.other());
+    // The king must not be able to hide behind itself when stepping away
+    // from a slider
+    let occupied = board.occupied() ^ from.into();
+    let safe = |pos: Position| (board.attackers(pos, occupied) & enemies).is_empty();
 
-impl<'b> Moves<'b> {
-    pub fn new(board: &'b Board, from: Position) -> Self {
-        Self {
-            board,
-            from,
-            color: board[from].unwrap().color,
-            state: 0,
+    let mut targets = Bitboard::EMPTY;
+    for pos in attacks(from) & !board.by_color(color) {
+        if safe(pos) {
+            targets.insert(pos);
         }
     }
-}
-
-impl<'b> Iterator for Moves<'b> {
-    type Item = Position;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let (x, y) = [
-                (1, 0),
-                (1, -1),
-                (0, -1),
-                (-1, -1),
-                (-1, 0),
-                (-1, 1),
-                (0, 1),
-                (1, 1),
-                (-2, 0),
-                (2, 0),
-            ]
-            .get(self.state as usize)?;
-            self.state += 1;
 
-            let checkcheck = |pos| !threatened_at(pos, &[self.from], &[], self.color, self.board);
-
-            let pos = match Position::new_i8(self.from.file() as i8 + x, self.from.rank() as i8 + y)
-            {
-                Some(pos) => pos,
-                None => {
-                    continue;
-                }
-            };
-
-            if *x == -2 {
-                let in_between = Position::new_unchecked(self.from.file() - 1, self.from.rank());
-                if !self.board.can_castle_queenside(self.color)
-                    || !checkcheck(self.from)
-                    || self.board[in_between].is_some()
-                    || !checkcheck(in_between)
-                {
-                    continue;
-                }
+    if from.rank() != color.home_rank() || !safe(from) {
+        return targets;
+    }
+    for kingside in [true, false] {
+        if let Some(rook) = board.castling_rook(color, kingside) {
+            if can_castle(board, from, rook, kingside, &safe) {
+                targets.insert(rook);
             }
+        }
+    }
 
-            if *x == 2 {
-                let in_between = Position::new_unchecked(self.from.file() + 1, self.from.rank());
-                if !self.board.can_castle_kingside(self.color)
-                    || !checkcheck(self.from)
-                    || self.board[in_between].is_some()
-                    || !checkcheck(in_between)
-                {
-                    continue;
-                }
-            }
+    targets
+}
 
-            break match self.board[pos] {
-                None if checkcheck(pos) => Some(pos),
-                Some(Piece { color: c, .. }) if c != self.color && checkcheck(pos) => Some(pos),
-                _ => continue,
-            };
-        }
+/// Checks that all tiles the king and rook pass over or end up on are
+/// empty, apart from the king and rook themselves, and that the king does
+/// not pass over or end up on an attacked tile. Castling is written as the
+/// king moving to the tile of the rook.
+fn can_castle(
+    board: &Board,
+    king: Position,
+    rook: Position,
+    kingside: bool,
+    safe: &impl Fn(Position) -> bool,
+) -> bool {
+    let king_to = board::castling_king_target(king, kingside);
+    let rook_to = board::castling_rook_target(king, kingside);
+    let king_path = bitboard::between(king, king_to) | king_to.into();
+    let rook_path = bitboard::between(rook, rook_to) | rook_to.into();
+    let castlers = Bitboard::from(king) | rook.into();
+    if !((king_path | rook_path) & board.occupied() & !castlers).is_empty() {
+        return false;
+    }
+    if !king_path.into_iter().all(safe) {
+        return false;
     }
+    // The rook may have been shielding the king's destination, which in
+    // Chess960 can be on the far side of the rook
+    let occupied = (board.occupied() & !castlers) | rook_to.into();
+    (board.attackers(king_to, occupied) & board.by_color(board[king].unwrap().color.other()))
+        .is_empty()
 }
@@ -1,4 +1,4 @@
-use crate::{Board, Color, Position};
+use crate::{bitboard, Bitboard, Board, Color, Piece, Position};
 
 use super::Kind;
 
@@ -11,6 +11,8 @@ use super::Kind;
 /// All positions in `treat_as_occupied` will be treated as if something with `color` stood there.
 ///
 /// Also, the piece in question does not have to be at `position` in `board`.
+///
+/// For the pieces actually attacking a tile, see `Board::attackers_of`.
 pub fn threatened_at(
     position: Position,
     treat_as_empty: &[Position],
@@ -18,191 +20,68 @@ pub fn threatened_at(
     color: Color,
     board: &Board,
 ) -> bool {
-    for (x, y) in [
-        (2, -1),
-        (1, -2),
-        (-1, -2),
-        (-2, -1),
-        (-2, 1),
-        (-1, 2),
-        (1, 2),
-        (2, 1),
-    ] {
-        let pos = match Position::new_i8(position.file() as i8 + x, position.rank() as i8 + y) {
-            Some(pos) => pos,
-            None => continue,
-        };
-        if treat_as_empty.contains(&pos) || treat_as_occupied.contains(&pos) {
-            continue;
-        }
-        if board[pos].map_or(false, |piece| {
-            piece.kind == Kind::Knight && piece.color == color.other()
-        }) {
-            return true;
-        }
-    }
-    for (x, y, k) in [
-        (0, 1, Kind::Rook),
-        (1, 0, Kind::Rook),
-        (0, -1, Kind::Rook),
-        (-1, 0, Kind::Rook),
-        (1, 1, Kind::Bishop),
-        (1, -1, Kind::Bishop),
-        (-1, -1, Kind::Bishop),
-        (-1, 1, Kind::Bishop),
-    ] {
-        for i in 1..8 {
-            let pos = match Position::new_i8(
-                position.file() as i8 + i * x,
-                position.rank() as i8 + i * y,
-            ) {
-                Some(pos) => pos,
-                None => break,
-            };
-            if treat_as_empty.contains(&pos) {
-                continue;
-            }
-            if treat_as_occupied.contains(&pos) {
-                break;
-            }
-            if let Some(piece) = board[pos] {
-                if piece.color != color && (piece.kind == k || piece.kind == Kind::Queen) {
-                    return true;
-                }
-                break;
-            }
-        }
+    let mut occupied = board.occupied();
+    let mut ignored = Bitboard::EMPTY;
+    for &pos in treat_as_empty.iter().chain(treat_as_occupied) {
+        occupied.remove(pos);
+        ignored.insert(pos);
     }
-    for (x, y) in [(-1, color.forwards()), (1, color.forwards())] {
-        let pos = match Position::new_i8(position.file() as i8 + x, position.rank() as i8 + y) {
-            Some(pos) => pos,
-            None => continue,
-        };
-        if treat_as_empty.contains(&pos) || treat_as_occupied.contains(&pos) {
-            continue;
-        }
-        if board[pos].map_or(false, |piece| {
-            piece.kind == Kind::Pawn && piece.color == color.other()
-        }) {
-            return true;
-        }
-    }
-    for (x, y) in [
-        (1, 0),
-        (1, -1),
-        (0, -1),
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, 1),
-        (1, 1),
-    ] {
-        let pos = match Position::new_i8(position.file() as i8 + x, position.rank() as i8 + y) {
-            Some(pos) => pos,
-            None => continue,
-        };
-        if treat_as_empty.contains(&pos) || treat_as_occupied.contains(&pos) {
-            continue;
-        }
-        if board[pos].map_or(false, |piece| {
-            piece.kind == Kind::King && piece.color == color.other()
-        }) {
-            return true;
-        }
+    for &pos in treat_as_occupied {
+        occupied.insert(pos);
     }
-    false
+    let attackers = board.attackers(position, occupied) & board.by_color(color.other());
+    !(attackers & !ignored).is_empty()
 }
 
-pub fn floating_checks(deltas: &[(i8, i8)], at: Position, color: Color, board: &Board) -> bool {
-    for (x, y) in deltas {
-        for i in 1..8 {
-            let pos = match Position::new_i8(at.file() as i8 + i * x, at.rank() as i8 + i * y) {
-                Some(pos) => pos,
-                None => break,
-            };
+/// Removes all tiles from `targets` that the piece at `from` can not move to
+/// without leaving its own king in check, or that are occupied by its own
+/// pieces.
+///
+/// Only valid for pieces other than the king, and does not handle en passant
+/// captures.
+pub(crate) fn legal_targets(board: &Board, from: Position, targets: Bitboard) -> Bitboard {
+    let color = board[from].unwrap().color;
+    let king = board.get_king_position(color);
+    let mut targets = targets & !board.by_color(color);
 
-            if let Some(piece) = board[pos] {
-                if piece.color == color.other() && piece.kind == Kind::King {
-                    return true;
-                } else {
-                    break;
-                }
-            }
+    let checkers = board.attackers_of(king, color.other());
+    match checkers.count() {
+        0 => {}
+        1 => {
+            let checker = checkers.first().unwrap();
+            targets &= bitboard::between(king, checker) | checker.into();
         }
+        _ => return Bitboard::EMPTY,
     }
-    false
-}
 
-pub struct Moves<'b> {
-    board: &'b Board,
-    from: Position,
-    color: Color,
-    dir_index: u8,
-    dist: i8,
-    dirs: &'static [(i8, i8)],
+    if pinner(board, from, color).is_some() {
+        targets &= bitboard::line(king, from);
+    }
+
+    targets
 }
 
-impl<'b> Moves<'b> {
-    pub fn new(board: &'b Board, from: Position, dirs: &'static [(i8, i8)]) -> Self {
-        Self {
-            board,
-            from,
-            color: board[from].unwrap().color,
-            dir_index: 0,
-            dist: 1,
-            dirs,
-        }
+/// Returns the position of the piece pinning the piece at `from` to the king
+/// of `color`, if any.
+pub(crate) fn pinner(board: &Board, from: Position, color: Color) -> Option<Position> {
+    let king = board.get_king_position(color);
+    let line = bitboard::line(king, from);
+    if line.is_empty() {
+        return None;
     }
+    let sliders = if king.file() == from.file() || king.rank() == from.rank() {
+        board.by_kind(Kind::Rook) | board.by_kind(Kind::Queen)
+    } else {
+        board.by_kind(Kind::Bishop) | board.by_kind(Kind::Queen)
+    };
+    let occupied = board.occupied();
+    (line & sliders & board.by_color(color.other()))
+        .into_iter()
+        .find(|&pinner| bitboard::between(king, pinner) & occupied == from.into())
 }
 
-impl<'b> Iterator for Moves<'b> {
-    type Item = Position;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let dir = self.dirs.get(self.dir_index as usize)?;
-            let pos = match Position::new_i8(
-                self.from.file() as i8 + dir.0 * self.dist,
-                self.from.rank() as i8 + dir.1 * self.dist,
-            ) {
-                Some(pos) => pos,
-                None => {
-                    self.dir_index += 1;
-                    self.dist = 1;
-                    continue;
-                }
-            };
-
-            break match self.board[pos].map(|p| p.color) {
-                None => {
-                    self.dist += 1;
-                    if threatened_at(
-                        self.board.get_king_position(self.color),
-                        &[self.from],
-                        &[pos],
-                        self.color,
-                        self.board,
-                    ) {
-                        continue;
-                    }
-                    Some(pos)
-                }
-                Some(c) => {
-                    self.dir_index += 1;
-                    self.dist = 1;
-                    if c == self.color
-                        || threatened_at(
-                            self.board.get_king_position(self.color),
-                            &[self.from],
-                            &[pos],
-                            self.color,
-                            self.board,
-                        )
-                    {
-                        continue;
-                    }
-                    Some(pos)
-                }
-            };
-        }
-    }
+/// Indicates if a piece of `color` attacking `attacks` is checking the
+/// opponent's king.
+pub(crate) fn attacks_king(attacks: Bitboard, color: Color, board: &Board) -> bool {
+    !(attacks & board.by_piece(Piece::new(color.other(), Kind::King))).is_empty()
 }
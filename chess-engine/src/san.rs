@@ -0,0 +1,190 @@
+use crate::{piece, Board, Error, Move, Position};
+
+fn file_char(pos: Position) -> char {
+    (pos.file() + b'a') as char
+}
+
+fn rank_char(pos: Position) -> char {
+    (b'8' - pos.rank()) as char
+}
+
+impl Move {
+    /// Returns the move as a string in standard algebraic notation, e.g.
+    /// "Nbxd7+", "exd6", "e8=Q#" or "O-O". `board` must be the state of the
+    /// board *before* the move is made.
+    ///
+    /// For moves that lead to pawn promotions, `promotion` must be set to the
+    /// kind of piece the pawn is turned in to. If not, `None` is returned.
+    ///
+    /// `None` is also returned if the move is not legal on `board`.
+    pub fn as_algebraic(&self, board: &Board) -> Option<String> {
+        let move_ = board.normalize_castling(*self);
+        let piece = board[move_.from]?;
+        if !board
+            .legal_moves()
+            .any(|m| m.from == move_.from && m.to == move_.to)
+        {
+            return None;
+        }
+        let is_promotion =
+            piece.kind == piece::Kind::Pawn && move_.to.rank() == piece.color.other().home_rank();
+        if is_promotion && !move_.promotion.is_some_and(|k| k.is_valid_promotion()) {
+            return None;
+        }
+
+        let mut san = String::new();
+        let delta_file = move_.to.file() as i8 - move_.from.file() as i8;
+
+        if let Some(kingside) = board.castling_side(move_) {
+            san.push_str(if kingside { "O-O" } else { "O-O-O" });
+        } else if piece.kind == piece::Kind::Pawn {
+            // A pawn changing file is always a capture, even en passant
+            if delta_file != 0 {
+                san.push(file_char(move_.from));
+                san.push('x');
+            }
+            san.push_str(&move_.to.to_string());
+            if let Some(kind) = move_.promotion.filter(|_| is_promotion) {
+                san.push('=');
+                san.push(kind.name());
+            }
+        } else {
+            san.push(piece.kind.name());
+
+            let rivals = board
+                .legal_moves()
+                .filter(|m| {
+                    m.to == move_.to && m.from != move_.from && board[m.from] == Some(piece)
+                })
+                .map(|m| m.from)
+                .collect::<Vec<_>>();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|p| p.file() != move_.from.file()) {
+                    san.push(file_char(move_.from));
+                } else if rivals.iter().all(|p| p.rank() != move_.from.rank()) {
+                    san.push(rank_char(move_.from));
+                } else {
+                    san.push(file_char(move_.from));
+                    san.push(rank_char(move_.from));
+                }
+            }
+
+            if board[move_.to].is_some() {
+                san.push('x');
+            }
+            san.push_str(&move_.to.to_string());
+        }
+
+        let mut after = board.clone();
+        let mut made = move_;
+        if !is_promotion {
+            made.promotion = None;
+        }
+        after.make_move_unchecked(made);
+        if after.is_check() {
+            san.push(if after.has_legal_moves() { '+' } else { '#' });
+        }
+
+        Some(san)
+    }
+    /// Parses a move in standard algebraic notation, e.g. "Nbd7", "exd6",
+    /// "e8=Q", "O-O" or "0-0-0", resolving it against the legal moves on
+    /// `board`. Trailing check, mate and annotation marks are ignored.
+    ///
+    /// Returns `Err(Error::ParsingError)` if `s` is not valid notation,
+    /// `Err(Error::IllegalMove)` if no legal move matches it,
+    /// `Err(Error::AmbiguousMove)` if more than one does and
+    /// `Err(Error::RequiresPromotion)` if a pawn reaches the last rank without
+    /// a promotion being specified.
+    pub fn from_san(s: &str, board: &Board) -> Result<Self, Error> {
+        let s = s.trim_end_matches(['+', '#', '!', '?']);
+        let color = board.next_to_move();
+
+        if let Some(kingside) = match s {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        } {
+            let from = board.get_king_position(color);
+            let to = board
+                .castling_rook(color, kingside)
+                .ok_or(Error::IllegalMove)?;
+            let move_ = Move::from((from, to));
+            return if board.legal_moves().any(|m| m == move_) {
+                Ok(move_)
+            } else {
+                Err(Error::IllegalMove)
+            };
+        }
+
+        let mut chars = s.chars().collect::<Vec<_>>();
+
+        let kind = match chars.first() {
+            Some(&c) if "KQRBN".contains(c) => {
+                chars.remove(0);
+                piece::Kind::from_name(c)?
+            }
+            _ => piece::Kind::Pawn,
+        };
+
+        let promotion = match chars.last() {
+            Some(&c) if "QRBN".contains(c) => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(piece::Kind::from_name(c)?)
+            }
+            _ => None,
+        };
+
+        if chars.len() < 2 {
+            return Err(Error::ParsingError);
+        }
+        let to = chars
+            .drain(chars.len() - 2..)
+            .collect::<String>()
+            .parse::<Position>()?;
+        let capture = chars.last() == Some(&'x');
+        if capture {
+            chars.pop();
+        }
+
+        let (mut from_file, mut from_rank) = (None, None);
+        for c in chars {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(c as u8 - b'a')
+                }
+                '1'..='8' if from_rank.is_none() => from_rank = Some(b'8' - c as u8),
+                _ => return Err(Error::ParsingError),
+            }
+        }
+        if kind == piece::Kind::Pawn && capture && from_file.is_none() {
+            return Err(Error::ParsingError);
+        }
+
+        // Castling is the king moving to its own rook, but can only be
+        // written as "O-O" or "O-O-O"
+        let mut candidates = board.legal_moves().filter(|&m| {
+            board[m.from].map(|p| p.kind) == Some(kind)
+                && board.castling_side(m).is_none()
+                && m.to == to
+                && from_file.is_none_or(|f| m.from.file() == f)
+                && from_rank.is_none_or(|r| m.from.rank() == r)
+        });
+        let mut move_ = candidates.next().ok_or(Error::IllegalMove)?;
+        if candidates.next().is_some() {
+            return Err(Error::AmbiguousMove);
+        }
+
+        let is_promotion = kind == piece::Kind::Pawn && to.rank() == color.other().home_rank();
+        match (is_promotion, promotion) {
+            (true, None) => return Err(Error::RequiresPromotion),
+            (false, Some(_)) => return Err(Error::IllegalMove),
+            _ => move_.promotion = promotion,
+        }
+
+        Ok(move_)
+    }
+}
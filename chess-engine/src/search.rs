@@ -0,0 +1,393 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    piece,
+    tt::{Bound, TranspositionTable},
+    Board, Game, Move, Undo,
+};
+
+/// Score of being checkmated right now. Mate in `n` plies is scored as
+/// `MATE - n`.
+const MATE: i32 = 30_000;
+/// Scores beyond this are mates.
+pub(crate) const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+const INFINITY: i32 = MATE + 1;
+/// The deepest the search will ever go, including quiescence search.
+const MAX_PLY: usize = 128;
+/// How many nodes are searched between checks of the clock and stop flag.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Constraints on how long a search may run. The search stops as soon as any
+/// of them is reached. Without any limits, it runs until stopped through
+/// `Searcher::stop_flag`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum depth in plies, not counting quiescence search
+    pub depth: Option<u8>,
+    /// Maximum number of positions to visit
+    pub nodes: Option<u64>,
+    /// Maximum time to spend
+    pub time: Option<Duration>,
+}
+
+/// The value of a position from the perspective of the player to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Material advantage in hundredths of a pawn
+    Centipawns(i32),
+    /// Mate in the given number of moves (not plies). Negative if the player
+    /// to move is getting mated.
+    Mate(i32),
+}
+
+impl Score {
+    fn from_internal(score: i32) -> Self {
+        if score > MATE_BOUND {
+            Self::Mate((MATE - score + 1) / 2)
+        } else if score < -MATE_BOUND {
+            Self::Mate(-(MATE + score) / 2)
+        } else {
+            Self::Centipawns(score)
+        }
+    }
+}
+
+/// The outcome of a search, or of one iteration of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found. `None` only if there are no legal moves.
+    pub best_move: Option<Move>,
+    pub score: Score,
+    /// The depth of the last completed iteration
+    pub depth: u8,
+    /// Number of positions visited, including in quiescence search
+    pub nodes: u64,
+    pub time: Duration,
+    /// Principal variation, i.e. the line of play expected from both sides,
+    /// starting with `best_move`
+    pub pv: Vec<Move>,
+}
+
+/// Negamax alpha-beta search with iterative deepening.
+///
+/// # Example use:
+/// ```rust
+/// # use chess_engine::{Board, Game, Limits, Searcher};
+/// let game = Game::new(Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap());
+/// let result = Searcher::new(Limits {
+///     depth: Some(3),
+///     ..Limits::default()
+/// })
+/// .search(&game);
+/// assert_eq!("a1a8", result.best_move.unwrap().as_arabic());
+/// ```
+#[derive(Debug)]
+pub struct Searcher {
+    limits: Limits,
+    stop: Arc<AtomicBool>,
+    start: Instant,
+    nodes: u64,
+    aborted: bool,
+    board: Board,
+    /// Keys of all positions in the game and the current search line, for
+    /// detecting repetitions
+    keys: Vec<u64>,
+    /// Principal variation of the last completed iteration, searched first
+    /// in the next one
+    prev_pv: Vec<Move>,
+    table: TranspositionTable,
+}
+
+impl Searcher {
+    /// Creates a searcher with a transposition table of the default size.
+    pub fn new(limits: Limits) -> Self {
+        Self::with_table(limits, TranspositionTable::default())
+    }
+    /// Creates a searcher using `table`, e.g. one kept from searching
+    /// earlier positions in the same game.
+    pub fn with_table(limits: Limits, table: TranspositionTable) -> Self {
+        Self {
+            limits,
+            stop: Arc::new(AtomicBool::new(false)),
+            start: Instant::now(),
+            nodes: 0,
+            aborted: false,
+            board: Board::default(),
+            keys: Vec::new(),
+            prev_pv: Vec::new(),
+            table,
+        }
+    }
+    /// Returns the transposition table, so that it can be reused for later
+    /// searches.
+    pub fn into_table(self) -> TranspositionTable {
+        self.table
+    }
+    /// A flag that stops the search when set, e.g. from another thread. The
+    /// best move of the last completed iteration is returned.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+    /// Searches for the best move for the player to move in `game`.
+    pub fn search(&mut self, game: &Game) -> SearchResult {
+        self.search_with(game, |_| {})
+    }
+    /// Same as `search`, but calls `on_iteration` with the result of each
+    /// completed iteration, e.g. to report progress.
+    pub fn search_with<F>(&mut self, game: &Game, mut on_iteration: F) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.aborted = false;
+        self.board = game.board().clone();
+        self.keys = game.position_keys().to_vec();
+        self.prev_pv.clear();
+        self.table.new_search();
+
+        let mut result = SearchResult {
+            best_move: None,
+            score: Score::Centipawns(0),
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            pv: Vec::new(),
+        };
+        let max_depth = self.limits.depth.unwrap_or(u8::MAX).min(MAX_PLY as u8 / 2);
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let score = self.negamax(depth, 0, -INFINITY, INFINITY, true, &mut pv);
+            if self.aborted {
+                // Moves from an unfinished iteration are still better than
+                // nothing at all
+                if result.best_move.is_none() {
+                    result.best_move = pv.first().copied();
+                    result.pv = pv;
+                }
+                break;
+            }
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score: Score::from_internal(score),
+                depth,
+                nodes: self.nodes,
+                time: self.start.elapsed(),
+                pv,
+            };
+            on_iteration(&result);
+            self.prev_pv = result.pv.clone();
+            if result.best_move.is_none() || score.abs() > MATE_BOUND {
+                break;
+            }
+        }
+        if result.best_move.is_none() {
+            result.best_move = self.board.legal_moves_with_promotions().next();
+        }
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result
+    }
+    /// Counts a visited node and checks whether the search must stop.
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        if self.limits.nodes.is_some_and(|n| self.nodes >= n) {
+            self.aborted = true;
+        } else if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.aborted = self.stop.load(Ordering::Relaxed)
+                || self.limits.time.is_some_and(|t| self.start.elapsed() >= t);
+        }
+        self.aborted
+    }
+    fn make_move(&mut self, move_: Move) -> Undo {
+        let undo = self.board.make_move_unchecked(move_);
+        self.keys.push(self.board.zobrist());
+        undo
+    }
+    fn unmake_move(&mut self, move_: Move, undo: Undo) {
+        self.keys.pop();
+        self.board.unmake_move(move_, undo);
+    }
+    /// Indicates if the current position is dead, drawn by the fifty-move
+    /// rule or has occurred before. A single repetition is treated as a draw, since
+    /// whatever could be achieved from the position the first time can be
+    /// achieved now as well.
+    fn is_draw(&self) -> bool {
+        if self.board.is_dead_position() {
+            return true;
+        }
+        // Checkmate on the move that reaches the limit still counts
+        if self.board.halfmove_counter >= 100 {
+            return !self.board.is_check() || self.board.has_legal_moves();
+        }
+        let current = self.keys.last().unwrap();
+        self.keys
+            .iter()
+            .rev()
+            .take(self.board.halfmove_counter as usize + 1)
+            .skip(2)
+            .step_by(2)
+            .any(|key| key == current)
+    }
+    fn negamax(
+        &mut self,
+        depth: u8,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        on_pv: bool,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        if ply > 0 && self.is_draw() {
+            return 0;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(ply, alpha, beta);
+        }
+        if self.visit() {
+            return 0;
+        }
+
+        let key = *self.keys.last().unwrap();
+        let entry = self.table.probe(key, ply);
+        if let Some(entry) = entry.filter(|e| ply > 0 && e.depth >= depth) {
+            match entry.bound {
+                Bound::Exact => {
+                    pv.extend(entry.best_move);
+                    return entry.score;
+                }
+                Bound::Lower if entry.score >= beta => return beta,
+                Bound::Upper if entry.score <= alpha => return alpha,
+                _ => {}
+            }
+        }
+
+        let pv_move = self.prev_pv.get(ply).copied().filter(|_| on_pv);
+        let moves = self.ordered_moves(false, pv_move.or(entry.and_then(|e| e.best_move)));
+        if moves.is_empty() {
+            return if self.board.is_check() {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        let mut child_pv = Vec::new();
+        for move_ in moves {
+            let undo = self.make_move(move_);
+            let score = -self.negamax(
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                pv_move == Some(move_),
+                &mut child_pv,
+            );
+            self.unmake_move(move_, undo);
+            if self.aborted {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(move_);
+                pv.clear();
+                pv.push(move_);
+                pv.extend_from_slice(&child_pv);
+                if alpha >= beta {
+                    alpha = beta;
+                    break;
+                }
+            }
+        }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(key, ply, depth, bound, alpha, best_move);
+        alpha
+    }
+    /// Searches captures only, until the position is quiet, so that the
+    /// evaluation is not made in the middle of an exchange. Promotions that
+    /// don't capture are left to the main search: here they would be a free
+    /// extra move at the horizon, making it look better to put them off.
+    fn quiescence(&mut self, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.visit() {
+            return 0;
+        }
+        let stand_pat = self.board.evaluate();
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        for move_ in self.ordered_moves(true, None) {
+            // Captures that lose material are very unlikely to do better
+            // than standing pat, so they aren't worth searching
+            if self.board.see(move_) < 0 {
+                continue;
+            }
+            let undo = self.make_move(move_);
+            let score = -self.quiescence(ply + 1, -beta, -alpha);
+            self.unmake_move(move_, undo);
+            if self.aborted {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        alpha
+    }
+    /// Returns the legal moves in the order they should be searched: `first`,
+    /// then captures with the most valuable victim and least valuable
+    /// attacker first, then the rest. If `tactical` is set, only captures are
+    /// returned, and only promoting to a queen if they promote.
+    fn ordered_moves(&self, tactical: bool, first: Option<Move>) -> Vec<Move> {
+        let board = &self.board;
+        let mut moves = board
+            .legal_moves_with_promotions()
+            .map(|m| {
+                let piece = board[m.from].unwrap();
+                let attacker = piece.kind;
+                // Castling is the king "capturing" its own rook
+                let victim = match board[m.to] {
+                    Some(p) if p.color != piece.color => Some(p.kind),
+                    Some(_) => None,
+                    None if attacker == piece::Kind::Pawn && m.from.file() != m.to.file() => {
+                        Some(piece::Kind::Pawn)
+                    }
+                    None => None,
+                };
+                let mut order = victim.map_or(0, |v| 10 * v.value() - attacker.value());
+                if let Some(kind) = m.promotion {
+                    order += kind.value();
+                }
+                if Some(m) == first {
+                    order = i32::MAX;
+                }
+                (m, order, victim.is_some())
+            })
+            .filter(|&(m, _, captures)| {
+                !tactical || captures && m.promotion.is_none_or(|k| k == piece::Kind::Queen)
+            })
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|&(_, order, _)| std::cmp::Reverse(order));
+        moves.into_iter().map(|(m, _, _)| m).collect()
+    }
+}
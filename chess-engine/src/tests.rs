@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, str::FromStr};
 
 use crate::*;
 
@@ -19,12 +19,311 @@ fn arabic_parsing() {
         ),
     ] {
         assert_eq!(Move::arabic(input), Ok(output), "at: {}", input);
+        assert_eq!(input, output.as_arabic());
     }
 }
 
+#[test]
+fn algebraic_notation() {
+    for (fen, arabic, san) in [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e4",
+            "e4",
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "g1f3",
+            "Nf3",
+        ),
+        ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", "O-O"),
+        ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8", "O-O-O"),
+        ("7k/8/8/8/8/5N2/8/1N4K1 w - - 0 1", "b1d2", "Nbd2"),
+        ("7k/8/8/8/R7/8/8/R5K1 w - - 0 1", "a1a3", "R1a3"),
+        ("4k3/8/8/8/8/Q1Q5/8/Q5K1 w - - 0 1", "a3b2", "Qa3b2"),
+        // The knight on e2 is pinned, so no disambiguation is needed
+        ("4r2k/8/8/8/8/8/N3N3/4K3 w - - 0 1", "a2c3", "Nc3"),
+        ("r6k/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8", "Rxa8+"),
+        ("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a7a8q", "a8=Q+"),
+        ("1r5k/P7/8/8/8/8/8/K7 w - - 0 1", "a7b8n", "axb8=N"),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+        (
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+            "f1b5",
+            "Bb5+",
+        ),
+        (
+            "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2",
+            "d8h4",
+            "Qh4#",
+        ),
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        let move_ = Move::arabic(arabic).unwrap();
+        assert_eq!(
+            Some(san.to_owned()),
+            move_.as_algebraic(&board),
+            "{} in {}",
+            arabic,
+            fen
+        );
+    }
+}
+
+#[test]
+fn algebraic_notation_fails() {
+    let board = Board::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+    // Missing promotion
+    assert_eq!(None, Move::arabic("a7a8").unwrap().as_algebraic(&board));
+    // Illegal promotion
+    assert_eq!(None, Move::arabic("a7a8k").unwrap().as_algebraic(&board));
+    // Illegal move
+    assert_eq!(None, Move::arabic("a1c3").unwrap().as_algebraic(&board));
+    // No piece
+    assert_eq!(None, Move::arabic("b1b2").unwrap().as_algebraic(&board));
+}
+
+#[test]
+fn algebraic_parsing_round_trip() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        "4k3/8/8/8/8/Q1Q5/8/Q5K1 w - - 0 1",
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        for mut move_ in board.legal_moves() {
+            if Game::new(board.clone()).missing_promotion(move_) {
+                move_.promotion = Some(piece::Kind::Rook);
+            }
+            let san = move_.as_algebraic(&board).unwrap();
+            assert_eq!(
+                Ok(move_),
+                Move::from_san(&san, &board),
+                "{} in {}",
+                san,
+                fen
+            );
+        }
+    }
+}
+
+#[test]
+fn algebraic_parsing() {
+    let game = Game::new(
+        Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap(),
+    );
+    for (san, arabic) in [
+        ("O-O", "e1h1"),
+        ("0-0-0", "e1a1"),
+        ("Nxd7", "e5d7"),
+        ("Ng4!?", "e5g4"),
+        ("dxe6", "d5e6"),
+        ("Qxf6+", "f3f6"),
+        ("Qxh3", "f3h3"),
+        ("Bd3", "e2d3"),
+        ("Rb1", "a1b1"),
+        ("Nb1", "c3b1"),
+    ] {
+        assert_eq!(
+            Ok(Move::arabic(arabic).unwrap()),
+            game.parse_san(san),
+            "{}",
+            san
+        );
+    }
+
+    let game = Game::new(Board::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap());
+    assert_eq!(Move::arabic("a7a8q"), game.parse_san("a8=Q+"));
+    assert_eq!(Move::arabic("a7a8n"), game.parse_san("a8N"));
+}
+
+#[test]
+fn algebraic_parsing_fails() {
+    let game = Game::new(Board::from_fen("7k/P7/8/8/8/8/1N3N2/K7 w - - 0 1").unwrap());
+    assert_eq!(Err(Error::AmbiguousMove), game.parse_san("Nd3"));
+    assert_eq!(Err(Error::IllegalMove), game.parse_san("Nd4"));
+    assert_eq!(Err(Error::IllegalMove), game.parse_san("O-O"));
+    assert_eq!(Err(Error::IllegalMove), game.parse_san("Kb1=Q"));
+    assert_eq!(Err(Error::RequiresPromotion), game.parse_san("a8"));
+
+    let game = Game::new(Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap());
+    for castling in ["Kh1", "Kxh1", "Ka1", "Kxa1"] {
+        assert_eq!(
+            Err(Error::IllegalMove),
+            game.parse_san(castling),
+            "{}",
+            castling
+        );
+    }
+    assert_eq!(Move::arabic("e1h1"), game.parse_san("O-O"));
+    for malformed in ["", "N", "Nz3", "Nd9", "e", "xe4", "Nbbd3", "Qa1b2c3"] {
+        assert_eq!(
+            Err(Error::ParsingError),
+            game.parse_san(malformed),
+            "{}",
+            malformed
+        );
+    }
+}
+
+const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+[ECO "C41"]
+
+1. e4 e5 2. Nf3 d6 {This is the Philidor Defence} 3. d4 Bg4?! 4. dxe5 Bxf3 5.
+Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 (8. Qxb7 Qb4+ 9. Qxb4 Bxb4+) 8... c6 9.
+Bg5 $1 b5 ; Black wants to get developed
+10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+
+Nxd7 16. Qb8+ $3 Nxb8 17. Rd8# 1-0
+"#;
+
+#[test]
+fn pgn_parsing() {
+    let pgn: Pgn = OPERA_GAME.parse().unwrap();
+    assert_eq!(Some("Paul Morphy"), pgn.tag("White"));
+    assert_eq!(Some("1-0"), pgn.tag("Result"));
+    assert_eq!(Some("C41"), pgn.tag("ECO"));
+    assert_eq!(None, pgn.tag("Annotator"));
+    assert_eq!(8, pgn.tags.len());
+    assert_eq!(33, pgn.game.history().len());
+    assert_eq!(
+        "1n1Rkb1r/p4ppp/4q3/4p1B1/4P3/8/PPP2PPP/2K5 b k - 1 17",
+        pgn.game.board().to_fen()
+    );
+}
+
+#[test]
+fn pgn_parsing_multiple_games() {
+    let pgns = Pgn::parse_all(&format!(
+        "{}\n{}",
+        OPERA_GAME,
+        r#"[Event "Short"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"]
+
+1.O-O-O Kf7 2.Rd7+ *"#
+    ))
+    .unwrap();
+    assert_eq!(2, pgns.len());
+    assert_eq!(Some("Short"), pgns[1].tag("Event"));
+    assert_eq!(
+        "8/3R1k2/8/8/8/8/8/2K5 b - - 3 2",
+        pgns[1].game.board().to_fen()
+    );
+}
+
+#[test]
+fn pgn_parsing_fails() {
+    use error::{PgnError, PgnErrorReason};
+
+    let err = |pgn: &str| match pgn.parse::<Pgn>() {
+        Err(Error::PgnError(err)) => err,
+        res => panic!("expected pgn error, got {:?}", res.map(|p| p.tags)),
+    };
+
+    assert_eq!(
+        PgnError {
+            ply: 4,
+            token: "Ke2".to_owned(),
+            reason: PgnErrorReason::Move(Box::new(Error::IllegalMove)),
+        },
+        err("1. e4 e5 2. Nf3 Ke2 *"),
+    );
+    assert_eq!(
+        PgnError {
+            ply: 3,
+            token: "2.Nd2".to_owned(),
+            reason: PgnErrorReason::Move(Box::new(Error::AmbiguousMove)),
+        },
+        err("[FEN \"4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1\"]\n1.Kf2 Ke7 2.Nd2 *"),
+    );
+    assert_eq!(
+        PgnErrorReason::MalformedTag,
+        err("[Event Paris]\n1. e4 *").reason
+    );
+    assert_eq!(
+        PgnErrorReason::UnterminatedComment,
+        err("1. e4 {never closed").reason
+    );
+    assert_eq!(
+        PgnErrorReason::UnterminatedVariation,
+        err("1. e4 (1. d4").reason
+    );
+    assert!(matches!(
+        err("[FEN \"8/8/8 w - - 0 1\"]\n*").reason,
+        PgnErrorReason::InvalidFen(_)
+    ));
+}
+
+#[test]
+fn pgn_export() {
+    let mut game = Game::new(Board::default());
+    for san in ["f3", "e5", "g4", "Qh4#"] {
+        let m = game.parse_san(san).unwrap();
+        game.make_move(m).unwrap();
+    }
+    assert_eq!(
+        r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "0-1"]
+[Termination "normal"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#,
+        game.to_pgn()
+    );
+}
+
+#[test]
+fn pgn_export_custom_start() {
+    let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 7").unwrap());
+    game.make_move(game.parse_san("Kf7").unwrap()).unwrap();
+    game.make_move(game.parse_san("O-O-O").unwrap()).unwrap();
+    let pgn = game.to_pgn();
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 7\"]\n"));
+    assert!(pgn.ends_with("\n7... Kf7 8. O-O-O *\n"), "{}", pgn);
+}
+
+#[test]
+fn pgn_round_trip() {
+    let mut pgn: Pgn = OPERA_GAME.parse().unwrap();
+    pgn.tags
+        .push(("Annotator".to_owned(), "Someone \"quoted\"".to_owned()));
+    let exported = pgn.to_string();
+
+    assert!(exported.lines().all(|line| line.len() <= 80));
+    assert!(exported.contains("[Annotator \"Someone \\\"quoted\\\"\"]"));
+    assert!(exported.contains("12. O-O-O Rd8"));
+    assert!(exported.trim_end().ends_with("17. Rd8# 1-0"));
+
+    let reimported: Pgn = exported.parse().unwrap();
+    // The game ended in mate, so the termination is added on export
+    assert_eq!(Some("normal"), reimported.tag("Termination"));
+    let tags = reimported
+        .tags
+        .iter()
+        .filter(|(name, _)| name != "Termination")
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(pgn.tags, tags);
+    assert_eq!(pgn.game.history(), reimported.game.history());
+}
+
 #[test]
 fn queen_cant_threaten_king_through_own_pieces() {
-    let game = Game::new(Board::from_fen("7K/8/8/4P1Q/1k/8/8/8 b - - 0 0").unwrap());
+    let game = Game::new(Board::from_fen("7K/8/8/4P1Q1/1k6/8/8/8 b - - 0 1").unwrap());
 
     assert!(game
         .all_legal_moves()
@@ -77,6 +376,7 @@ fn arabic_parsing_fails() {
     assert!(matches!(Move::arabic("a4a4 "), Err(Error::ParsingError)));
     assert!(matches!(Move::arabic(" a4a4"), Err(Error::ParsingError)));
     assert!(matches!(Move::arabic("a4a4l"), Err(Error::ParsingError)));
+    assert!(matches!(Move::arabic("é2e4"), Err(Error::ParsingError)));
 }
 
 #[test]
@@ -103,12 +403,12 @@ fn piece_parsing_fail() {
     }
 }
 
-fn perft(game: Game, depth: usize) -> usize {
+fn perft(game: &mut Game, depth: usize) -> usize {
     if depth == 0 {
         return 1;
     }
     let mut ans = 0;
-    for mut move_ in game.all_legal_moves() {
+    for mut move_ in game.all_legal_moves().collect::<Vec<_>>() {
         if game.missing_promotion(move_) {
             for kind in [
                 piece::Kind::Bishop,
@@ -117,14 +417,14 @@ fn perft(game: Game, depth: usize) -> usize {
                 piece::Kind::Rook,
             ] {
                 move_.promotion = Some(kind);
-                let mut g = Game::new(game.board().clone());
-                g.make_move(move_).unwrap();
-                ans += perft(g, depth - 1);
+                game.make_move(move_).unwrap();
+                ans += perft(game, depth - 1);
+                game.unmake_move();
             }
         } else {
-            let mut g = Game::new(game.board().clone());
-            g.make_move(move_).unwrap();
-            ans += perft(g, depth - 1);
+            game.make_move(move_).unwrap();
+            ans += perft(game, depth - 1);
+            game.unmake_move();
         }
     }
     ans
@@ -132,65 +432,321 @@ fn perft(game: Game, depth: usize) -> usize {
 
 #[test]
 fn perft_1() {
-    let game = Game::new(
+    let mut game = Game::new(
         Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap(),
     );
-    assert_eq!(20, perft(game.clone(), 1));
-    assert_eq!(400, perft(game.clone(), 2));
-    assert_eq!(8902, perft(game.clone(), 3));
-    // assert_eq!(197281, perft(game.clone(), 4));
+    assert_eq!(20, perft(&mut game, 1));
+    assert_eq!(400, perft(&mut game, 2));
+    assert_eq!(8902, perft(&mut game, 3));
+    assert_eq!(197281, perft(&mut game, 4));
 }
 
 #[test]
 fn perft_2() {
-    let game = Game::new(
+    let mut game = Game::new(
         Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
             .unwrap(),
     );
-    assert_eq!(48, perft(game.clone(), 1));
-    assert_eq!(2039, perft(game.clone(), 2));
-    // assert_eq!(97862, perft(game.clone(), 3));
-    // assert_eq!(4085603, perft(game.clone(), 4));
+    assert_eq!(48, perft(&mut game, 1));
+    assert_eq!(2039, perft(&mut game, 2));
+    assert_eq!(97862, perft(&mut game, 3));
+    assert_eq!(4085603, perft(&mut game, 4));
 }
 
 #[test]
 fn perft_3() {
-    let game = Game::new(Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap());
-    assert_eq!(14, perft(game.clone(), 1));
-    // assert_eq!(191, perft(game.clone(), 2));
-    // assert_eq!(2812, perft(game.clone(), 3));
+    let mut game = Game::new(Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap());
+    assert_eq!(14, perft(&mut game, 1));
+    assert_eq!(191, perft(&mut game, 2));
+    assert_eq!(2812, perft(&mut game, 3));
 }
 
 #[test]
 fn perft_4() {
-    let game = Game::new(
+    let mut game = Game::new(
         Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
             .unwrap(),
     );
-    assert_eq!(6, perft(game.clone(), 1));
-    assert_eq!(264, perft(game.clone(), 2));
-    // assert_eq!(9467, perft(game.clone(), 3));
+    assert_eq!(6, perft(&mut game, 1));
+    assert_eq!(264, perft(&mut game, 2));
+    assert_eq!(9467, perft(&mut game, 3));
 }
 
 #[test]
 fn perft_5() {
-    let game = Game::new(
+    let mut game = Game::new(
         Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap(),
     );
-    assert_eq!(44, perft(game.clone(), 1));
-    assert_eq!(1486, perft(game.clone(), 2));
-    // assert_eq!(62379, perft(game.clone(), 3));
+    assert_eq!(44, perft(&mut game, 1));
+    assert_eq!(1486, perft(&mut game, 2));
+    assert_eq!(62379, perft(&mut game, 3));
 }
 
 #[test]
 fn perft_6() {
-    let game = Game::new(
+    let mut game = Game::new(
         Board::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10")
             .unwrap(),
     );
-    assert_eq!(46, perft(game.clone(), 1));
-    assert_eq!(2079, perft(game.clone(), 2));
-    // assert_eq!(89890, perft(game.clone(), 3));
+    assert_eq!(46, perft(&mut game, 1));
+    assert_eq!(2079, perft(&mut game, 2));
+    assert_eq!(89890, perft(&mut game, 3));
+}
+
+#[test]
+fn unmake_move_restores_board() {
+    fn check(game: &mut Game, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        let before = game.board().clone();
+        for mut move_ in game.all_legal_moves().collect::<Vec<_>>() {
+            if game.missing_promotion(move_) {
+                move_.promotion = Some(piece::Kind::Knight);
+            }
+            game.make_move(move_).unwrap();
+            check(game, depth - 1);
+            assert_eq!(Some(move_), game.unmake_move());
+            assert_eq!(&before, game.board(), "after {}", move_.as_arabic());
+        }
+    }
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ] {
+        check(&mut Game::new(Board::from_fen(fen).unwrap()), 3);
+    }
+}
+
+#[test]
+fn unmake_move_ignores_stray_promotion() {
+    let before = Board::from_fen("4k3/Q7/8/8/8/8/8/1N2K3 w - - 0 1").unwrap();
+    for m in ["b1c3q", "e1d1q", "a7a6n", "a7a8q"] {
+        let m = Move::arabic(m).unwrap();
+        let mut board = before.clone();
+        let undo = board.make_move_unchecked(m);
+        board.unmake_move(m, undo);
+        assert_eq!(before, board, "{}", m.as_arabic());
+    }
+}
+
+#[test]
+fn unmake_move_without_history() {
+    let mut game = Game::new(Board::default());
+    assert_eq!(None, game.unmake_move());
+    assert_eq!(&Board::default(), game.board());
+}
+
+#[test]
+fn history_records_moves() {
+    let mut game = Game::new(Board::default());
+    let moves = ["e2e4", "e7e5", "g1f3"].map(|m| Move::arabic(m).unwrap());
+    for m in moves {
+        game.make_move(m).unwrap();
+    }
+    assert_eq!(&moves, game.history());
+    game.unmake_move();
+    assert_eq!(&moves[..2], game.history());
+}
+
+#[test]
+fn threefold_repetition() {
+    let mut game = Game::new(Board::default());
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"].map(|m| Move::arabic(m).unwrap());
+
+    assert_eq!(1, game.repetition_count());
+    for m in shuffle {
+        assert_eq!(Ok(GameState::Ongoing), game.make_move(m));
+    }
+    assert_eq!(2, game.repetition_count());
+    assert_eq!(Err(Error::NoDrawToClaim), game.claim_draw());
+    for m in &shuffle[..3] {
+        assert_eq!(Ok(GameState::Ongoing), game.make_move(*m));
+    }
+    assert_eq!(
+        Ok(GameState::DrawClaimable(DrawReason::ThreefoldRepetition)),
+        game.make_move(shuffle[3])
+    );
+    assert_eq!(3, game.repetition_count());
+
+    game.unmake_move();
+    assert_eq!(2, game.repetition_count());
+    assert_eq!(GameState::Ongoing, game.state());
+    game.make_move(shuffle[3]).unwrap();
+
+    // The draw is claimable as long as the position keeps repeating, and
+    // automatic once it has occurred five times
+    for m in &shuffle[..3] {
+        game.make_move(*m).unwrap();
+    }
+    assert_eq!(
+        Ok(GameState::DrawClaimable(DrawReason::ThreefoldRepetition)),
+        game.make_move(shuffle[3])
+    );
+    let mut claimed = game.clone();
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Draw(
+            DrawReason::ThreefoldRepetition
+        ))),
+        claimed.claim_draw()
+    );
+    for m in &shuffle[..3] {
+        game.make_move(*m).unwrap();
+    }
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Draw(
+            DrawReason::FivefoldRepetition
+        ))),
+        game.make_move(shuffle[3])
+    );
+    assert_eq!(5, game.repetition_count());
+}
+
+#[test]
+fn fifty_and_seventy_five_move_rules() {
+    let fen = |halfmoves: u16| format!("4k3/8/8/8/8/8/4P3/R3K3 w - - {} 60", halfmoves);
+    let move_ = Move::arabic("a1a2").unwrap();
+
+    let mut game = Game::new(Board::from_fen(&fen(98)).unwrap());
+    assert_eq!(Ok(GameState::Ongoing), game.make_move(move_));
+    assert_eq!(Err(Error::NoDrawToClaim), game.claim_draw());
+    assert_eq!(
+        Ok(GameState::DrawClaimable(DrawReason::FiftyMoveRule)),
+        game.make_move(Move::arabic("e8d8").unwrap())
+    );
+    // A pawn move resets the counter
+    let mut game = Game::new(Board::from_fen(&fen(99)).unwrap());
+    assert_eq!(
+        Ok(GameState::Ongoing),
+        game.make_move(Move::arabic("e2e4").unwrap())
+    );
+
+    let mut game = Game::new(Board::from_fen(&fen(148)).unwrap());
+    assert_eq!(
+        Ok(GameState::DrawClaimable(DrawReason::FiftyMoveRule)),
+        game.make_move(move_)
+    );
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Draw(DrawReason::FiftyMoveRule))),
+        game.claim_draw()
+    );
+    assert_eq!(
+        Some(Outcome::Draw(DrawReason::FiftyMoveRule)),
+        game.outcome()
+    );
+    // Taking back the move also takes back the claim
+    game.unmake_move();
+    assert_eq!(None, game.outcome());
+    assert_eq!(
+        GameState::DrawClaimable(DrawReason::FiftyMoveRule),
+        game.state()
+    );
+    game.make_move(move_).unwrap();
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Draw(
+            DrawReason::SeventyFiveMoveRule
+        ))),
+        game.make_move(Move::arabic("e8d8").unwrap())
+    );
+
+    // Checkmate takes precedence over the seventy-five-move rule
+    let mut game = Game::new(Board::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 149 80").unwrap());
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Decisive {
+            winner: Color::White,
+            reason: WinReason::Checkmate
+        })),
+        game.make_move(Move::arabic("a1a8").unwrap())
+    );
+}
+
+#[test]
+fn repetition_ignores_uncapturable_en_passant_square() {
+    // The en passant square after e2e4 does not make the position different
+    // since no black pawn can capture there
+    let mut game = Game::new(
+        Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap(),
+    );
+    game.make_move(Move::arabic("e2e4").unwrap()).unwrap();
+    for m in ["g8f6", "g1f3", "f6g8", "f3g1"] {
+        game.make_move(Move::arabic(m).unwrap()).unwrap();
+    }
+    assert_eq!(2, game.repetition_count());
+}
+
+#[test]
+fn zobrist_is_updated_incrementally() {
+    fn check(game: &mut Game, depth: usize) {
+        let fresh = Board::from_fen(&game.board().to_fen()).unwrap();
+        assert_eq!(
+            fresh.zobrist(),
+            game.board().zobrist(),
+            "{}",
+            fresh.to_fen()
+        );
+        if depth == 0 {
+            return;
+        }
+        for mut move_ in game.all_legal_moves().collect::<Vec<_>>() {
+            if game.missing_promotion(move_) {
+                move_.promotion = Some(piece::Kind::Queen);
+            }
+            game.make_move(move_).unwrap();
+            check(game, depth - 1);
+            game.unmake_move();
+        }
+    }
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ] {
+        check(&mut Game::new(Board::from_fen(fen).unwrap()), 3);
+    }
+}
+
+#[test]
+fn zobrist_of_transpositions() {
+    let play = |moves: &[&str]| {
+        let mut game = Game::new(Board::default());
+        for m in moves {
+            game.make_move(Move::arabic(m).unwrap()).unwrap();
+        }
+        game.board().zobrist()
+    };
+
+    assert_eq!(
+        play(&["e2e4", "e7e5", "g1f3"]),
+        play(&["g1f3", "e7e5", "e2e4"]),
+    );
+    // Same pieces, but different players to move
+    assert_ne!(play(&["g1f3", "g8f6", "f3g1"]), play(&[]));
+    // Same pieces, but white has lost the right to castle
+    assert_ne!(
+        play(&["e2e4", "e7e5", "e1e2", "e8e7", "e2e1", "e7e8"]),
+        Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3")
+            .unwrap()
+            .zobrist(),
+    );
+}
+
+#[test]
+fn set_tile_updates_bitboards() {
+    let mut board = Board::default();
+    let e2 = Position::from_str("e2").unwrap();
+    let e4 = Position::from_str("e4").unwrap();
+    let pawn = board.set_tile(e2, None);
+    board.set_tile(e4, pawn);
+
+    assert_eq!(board[e4], Some(Piece::new(Color::White, piece::Kind::Pawn)));
+    assert!(board.by_kind(piece::Kind::Pawn).contains(e4));
+    assert!(!board.by_kind(piece::Kind::Pawn).contains(e2));
+    assert!(board.by_color(Color::White).contains(e4));
+    assert!(!board.occupied().contains(e2));
+    assert_eq!(16, board.by_color(Color::White).count());
 }
 
 #[test]
@@ -228,8 +784,9 @@ fn few_simple_moves() {
 
 #[test]
 fn default_board() {
+    let board = Board::default();
     assert_eq!(
-        Board::default(),
+        board,
         Board {
             tiles: [
                 [
@@ -313,14 +870,1238 @@ fn default_board() {
                     }),
                 ],
             ],
+            kinds: [
+                Bitboard(0x00ff_0000_0000_ff00),
+                Bitboard(0x8100_0000_0000_0081),
+                Bitboard(0x4200_0000_0000_0042),
+                Bitboard(0x2400_0000_0000_0024),
+                Bitboard(0x0800_0000_0000_0008),
+                Bitboard(0x1000_0000_0000_0010),
+            ],
+            colors: [Bitboard(0xffff_0000_0000_0000), Bitboard(0xffff)],
             next_to_move: Color::White,
-            can_castle_white_kingside: true,
-            can_castle_white_queenside: true,
-            can_castle_black_kingside: true,
-            can_castle_black_queenside: true,
+            castling: [Some(7), Some(0), Some(7), Some(0)],
+            chess960: false,
             en_passant_square: None,
             halfmove_counter: 0,
             move_number: 1,
+            hash: board.compute_hash(),
+        }
+    );
+}
+
+fn search_depth(fen: &str, depth: u8) -> SearchResult {
+    let game = Game::new(Board::from_fen(fen).unwrap());
+    Searcher::new(Limits {
+        depth: Some(depth),
+        ..Limits::default()
+    })
+    .search(&game)
+}
+
+#[test]
+fn search_finds_mate() {
+    // Back rank mate in one
+    let result = search_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 4);
+    assert_eq!(Some(Move::arabic("a1a8").unwrap()), result.best_move);
+    assert_eq!(Score::Mate(1), result.score);
+    assert_eq!(1, result.pv.len());
+
+    // Mate in two, where the principal variation must end in mate
+    let fen = "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 10";
+    let result = search_depth(fen, 4);
+    assert_eq!(Score::Mate(2), result.score);
+    assert_eq!(3, result.pv.len());
+    let mut game = Game::new(Board::from_fen(fen).unwrap());
+    let mut state = GameState::Ongoing;
+    for &move_ in &result.pv {
+        state = game.make_move(move_).unwrap();
+    }
+    assert_eq!(
+        GameState::Over(Outcome::Decisive {
+            winner: Color::White,
+            reason: WinReason::Checkmate
+        }),
+        state
+    );
+
+    // Mate on the move reaching the fifty-move limit is still mate
+    let result = search_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80", 4);
+    assert_eq!(Some(Move::arabic("a1a8").unwrap()), result.best_move);
+    assert_eq!(Score::Mate(1), result.score);
+
+    // Getting mated
+    let result = search_depth("k7/8/8/8/8/2r5/1r6/7K w - - 0 1", 4);
+    assert_eq!(Some(Move::arabic("h1g1").unwrap()), result.best_move);
+    assert_eq!(Score::Mate(-1), result.score);
+}
+
+#[test]
+fn search_captures() {
+    // A hanging queen is taken, but a defended one is not
+    let result = search_depth("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 1);
+    assert_eq!(Some(Move::arabic("d2d5").unwrap()), result.best_move);
+    let result = search_depth("4k3/4n3/8/3r4/8/8/3Q4/4K3 w - - 0 1", 2);
+    assert_ne!(Some(Move::arabic("d2d5").unwrap()), result.best_move);
+}
+
+#[test]
+fn search_promotes() {
+    // At even depths, promoting last used to look better than promoting now
+    for fen in [
+        "8/P6k/8/8/8/8/8/K7 w - - 0 1",
+        "8/P5k1/8/8/8/8/8/K7 w - - 0 1",
+    ] {
+        let result = search_depth(fen, 2);
+        assert_eq!(
+            Some(Move::arabic("a7a8q").unwrap()),
+            result.best_move,
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn search_without_moves() {
+    let result = search_depth("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+    assert_eq!(None, result.best_move);
+    assert_eq!(Score::Centipawns(0), result.score);
+}
+
+#[test]
+fn search_limits() {
+    let game = Game::new(Board::default());
+    let result = Searcher::new(Limits {
+        nodes: Some(5000),
+        ..Limits::default()
+    })
+    .search(&game);
+    assert!(result.best_move.is_some());
+    assert_eq!(5000, result.nodes);
+
+    let searcher = Searcher::new(Limits::default());
+    searcher
+        .stop_flag()
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    let result = { searcher }.search(&game);
+    assert!(result.best_move.is_some());
+
+    let result = Searcher::new(Limits {
+        time: Some(std::time::Duration::from_millis(50)),
+        ..Limits::default()
+    })
+    .search(&game);
+    assert!(result.best_move.is_some());
+    assert!(result.time < std::time::Duration::from_secs(1));
+}
+
+/// Returns the FEN of the same position with the colors swapped and the board
+/// mirrored vertically.
+fn flip_fen(fen: &str) -> String {
+    let fields = fen.split(' ').collect::<Vec<_>>();
+    let swap_case = |s: &str| {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                }
+            })
+            .collect::<String>()
+    };
+    let pieces = fields[0].split('/').rev().collect::<Vec<_>>().join("/");
+    let next_to_move = if fields[1] == "w" { "b" } else { "w" };
+    let mut castling = swap_case(fields[2]).chars().collect::<Vec<_>>();
+    castling.sort_by_key(|c| (c.is_ascii_lowercase(), *c != 'K' && *c != 'k'));
+    let en_passant = match fields[3] {
+        "-" => "-".to_owned(),
+        ep => format!("{}{}", &ep[..1], 9 - ep[1..].parse::<u8>().unwrap()),
+    };
+    format!(
+        "{} {} {} {} {} {}",
+        swap_case(&pieces),
+        next_to_move,
+        castling.into_iter().collect::<String>(),
+        en_passant,
+        fields[4],
+        fields[5]
+    )
+}
+
+#[test]
+fn evaluation_is_symmetric() {
+    assert_eq!(0, Board::default().evaluate());
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        let flipped = Board::from_fen(&flip_fen(fen)).unwrap();
+        assert_eq!(board.evaluate(), flipped.evaluate(), "{}", fen);
+    }
+}
+
+#[test]
+fn evaluation_terms() {
+    let eval = |fen: &str| Board::from_fen(fen).unwrap().evaluate();
+
+    // Material, from the perspective of the player to move
+    assert!(eval("4k3/8/8/8/8/8/8/3QK3 w - - 0 1") > 800);
+    assert!(eval("4k3/8/8/8/8/8/8/3QK3 b - - 0 1") < -800);
+
+    // Passed pawns are worth more the further they have advanced
+    assert!(eval("4k3/8/8/8/8/8/P7/4K3 w - - 0 1") < eval("4k3/P7/8/8/8/8/8/4K3 w - - 0 1"));
+    // A pawn blocked by an enemy pawn on a neighbouring file is not passed
+    assert!(eval("4k3/1p6/8/P7/8/8/8/4K3 w - - 0 1") < eval("4k3/7p/8/P7/8/8/8/4K3 w - - 0 1"));
+
+    // Doubled and isolated pawns
+    assert!(eval("4k3/8/8/8/8/8/PP6/4K3 w - - 0 1") > eval("4k3/8/8/8/8/P7/P7/4K3 w - - 0 1"));
+    assert!(eval("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1") > eval("4k3/pp6/8/8/8/8/P1P5/4K3 w - - 0 1"));
+}
+
+#[test]
+fn perft_with_transposition_table() {
+    let board =
+        Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    assert_eq!(97862, board.perft(3));
+    let mut table = TranspositionTable::new(1);
+    assert_eq!(97862, board.perft_cached(3, &mut table));
+    // Again, with the counts already in the table
+    assert_eq!(97862, board.perft_cached(3, &mut table));
+    assert_eq!(2039, board.perft_cached(2, &mut table));
+    // A table too small to hold anything useful must still give the right count
+    let mut table = TranspositionTable::new(0);
+    assert_eq!(1, table.capacity());
+    assert_eq!(97862, board.perft_cached(3, &mut table));
+}
+
+#[test]
+fn divide() {
+    let board =
+        Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    let counts = board.divide(3);
+    assert_eq!(48, counts.len());
+    assert_eq!(97862, counts.iter().map(|(_, n)| n).sum::<u64>());
+    let castling = counts
+        .iter()
+        .find(|(m, _)| board.display_castling(*m) == Move::arabic("e1g1").unwrap())
+        .unwrap();
+    assert_eq!(2059, castling.1);
+    let mut table = TranspositionTable::new(1);
+    assert_eq!(counts, board.divide_cached(3, &mut table));
+    assert!(board.divide(0).is_empty());
+}
+
+#[test]
+fn transposition_table() {
+    use tt::{Bound, Entry};
+
+    let mut table = TranspositionTable::<Entry>::new(1);
+    let move_ = Move::arabic("e2e4").unwrap();
+    assert_eq!(None, table.get(1));
+    table.store(1, 0, 3, Bound::Exact, 25, Some(move_));
+    let entry = table.probe(1, 0).unwrap();
+    assert_eq!(
+        (3, Bound::Exact, 25, Some(move_)),
+        (entry.depth, entry.bound, entry.score, entry.best_move)
+    );
+
+    // Entries for the same position are always replaced
+    table.store(1, 0, 1, Bound::Lower, 40, None);
+    assert_eq!(1, table.probe(1, 0).unwrap().depth);
+
+    // Mate scores are stored relative to the position, so that the same
+    // mate found at a different distance from the root gets the right score
+    let mate_in_3 = 30_000 - 5;
+    table.store(2, 2, 3, Bound::Exact, mate_in_3, None);
+    assert_eq!(30_000 - 3, table.get(2).unwrap().score);
+    assert_eq!(30_000 - 7, table.probe(2, 4).unwrap().score);
+    table.store(2, 2, 3, Bound::Exact, -mate_in_3, None);
+    assert_eq!(-(30_000 - 7), table.probe(2, 4).unwrap().score);
+
+    // With a single slot, deeper entries from the current search are kept,
+    // but entries from earlier searches are replaced
+    let mut table = TranspositionTable::<Entry>::new(0);
+    table.store(1, 0, 5, Bound::Exact, 0, None);
+    table.store(2, 0, 4, Bound::Exact, 0, None);
+    assert!(table.get(1).is_some() && table.get(2).is_none());
+    table.new_search();
+    table.store(2, 0, 4, Bound::Exact, 0, None);
+    assert!(table.get(1).is_none() && table.get(2).is_some());
+    table.clear();
+    assert_eq!(None, table.get(2));
+}
+
+#[test]
+fn search_reuses_transposition_table() {
+    let game = Game::new(
+        Board::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 10")
+            .unwrap(),
+    );
+    let limits = Limits {
+        depth: Some(4),
+        ..Limits::default()
+    };
+    let mut searcher = Searcher::new(limits);
+    let first = searcher.search(&game);
+    let mut searcher = Searcher::with_table(limits, searcher.into_table());
+    let second = searcher.search(&game);
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(Score::Mate(2), second.score);
+    assert!(second.nodes < first.nodes);
+}
+
+#[test]
+fn insufficient_material() {
+    use Color::*;
+    for (fen, white, black) in [
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1", true, true),
+        // Bishops all on dark tiles, of both colors
+        ("4kb2/8/8/8/8/8/8/2B1K1B1 w - - 0 1", true, true),
+        // Bishops on both colors
+        ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", false, true),
+        ("4kb2/8/8/8/8/8/8/4KB2 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1", false, true),
+        // A knight can mate a king boxed in by its own pieces
+        ("4k3/4r3/8/8/8/8/8/2N1K3 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/2N1K2q w - - 0 1", true, false),
+        // A bishop can mate a king boxed in by its own pawns or knights
+        ("4k3/4p3/8/8/8/8/8/2B1K3 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/2B1K1n1 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", false, true),
+        ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false, true),
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(white, board.has_insufficient_material(White), "{}", fen);
+        assert_eq!(black, board.has_insufficient_material(Black), "{}", fen);
+    }
+}
+
+#[test]
+fn dead_position_is_draw() {
+    let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/4r3/4K1N1 w - - 0 1").unwrap());
+    assert_eq!(GameState::Ongoing, game.state());
+    assert_eq!(
+        GameState::Over(Outcome::Draw(DrawReason::InsufficientMaterial)),
+        game.make_move(Move::arabic("e1e2").unwrap()).unwrap()
+    );
+
+    let game = Game::new(Board::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap());
+    assert_eq!(GameState::Ongoing, game.state());
+}
+
+#[test]
+fn timeout() {
+    use Color::*;
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    let mut game = Game::new(board.clone());
+    let win = Outcome::Decisive {
+        winner: White,
+        reason: WinReason::Timeout,
+    };
+    assert_eq!(Ok(GameState::Over(win)), game.timeout(Black));
+    assert_eq!(Err(Error::GameOver), game.timeout(White));
+    assert_eq!(Some(win), game.outcome());
+    assert_eq!("time forfeit", win.termination());
+    // A lone king can not win on time
+    let mut game = Game::new(board);
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Draw(
+            DrawReason::TimeoutVsInsufficientMaterial
+        ))),
+        game.timeout(White)
+    );
+    let mut game = Game::new(Board::default());
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Decisive {
+            winner: Black,
+            reason: WinReason::Timeout
+        })),
+        game.timeout(White)
+    );
+    assert_eq!("0-1", game.outcome().unwrap().result());
+    let pgn = game.to_pgn();
+    assert!(pgn.contains("[Result \"0-1\"]\n[Termination \"time forfeit\"]\n"));
+    assert!(pgn.ends_with("\n0-1\n"));
+}
+
+#[test]
+fn resignation() {
+    let mut game = Game::new(Board::default());
+    game.make_move(Move::arabic("e2e4").unwrap()).unwrap();
+    let outcome = Outcome::Decisive {
+        winner: Color::White,
+        reason: WinReason::Resignation,
+    };
+    assert_eq!(Ok(GameState::Over(outcome)), game.resign(Color::Black));
+    assert_eq!(Some(outcome), game.outcome());
+    assert_eq!(Err(Error::GameOver), game.resign(Color::White));
+    assert_eq!(
+        Err(Error::GameOver),
+        game.make_move(Move::arabic("e7e5").unwrap())
+    );
+    assert!(game.to_pgn().ends_with("\n1. e4 1-0\n"));
+}
+
+#[test]
+fn draw_offers() {
+    let mut game = Game::new(Board::default());
+    assert_eq!(Err(Error::NoDrawOffer), game.accept_draw(Color::White));
+    assert_eq!(Err(Error::NoDrawOffer), game.decline_draw(Color::White));
+
+    // Only the opponent can answer an offer
+    game.offer_draw(Color::White).unwrap();
+    assert_eq!(Err(Error::NoDrawOffer), game.accept_draw(Color::White));
+    assert_eq!(Err(Error::NoDrawOffer), game.decline_draw(Color::White));
+    assert_eq!(None, game.outcome());
+
+    // Declined explicitly
+    assert_eq!(Some(Color::White), game.draw_offer());
+    assert_eq!(Ok(()), game.decline_draw(Color::Black));
+    assert_eq!(None, game.draw_offer());
+
+    // The offer stands when the player offering moves, but not when the
+    // opponent does
+    game.offer_draw(Color::White).unwrap();
+    game.make_move(Move::arabic("e2e4").unwrap()).unwrap();
+    assert_eq!(Some(Color::White), game.draw_offer());
+    game.make_move(Move::arabic("e7e5").unwrap()).unwrap();
+    assert_eq!(None, game.draw_offer());
+
+    game.offer_draw(Color::Black).unwrap();
+    assert_eq!(
+        Ok(GameState::Over(Outcome::Draw(DrawReason::Agreement))),
+        game.accept_draw(Color::White)
+    );
+    assert_eq!(None, game.draw_offer());
+    assert_eq!(Err(Error::GameOver), game.offer_draw(Color::White));
+    assert_eq!(Err(Error::GameOver), game.accept_draw(Color::White));
+    assert_eq!(
+        Err(Error::GameOver),
+        game.make_move(Move::arabic("g1f3").unwrap())
+    );
+}
+
+#[test]
+fn no_moves_after_checkmate() {
+    let mut game = Game::new(Board::default());
+    for m in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+        game.make_move(Move::arabic(m).unwrap()).unwrap();
+    }
+    assert_eq!(
+        Err(Error::GameOver),
+        game.make_move(Move::arabic("e1f2").unwrap())
+    );
+    assert_eq!(Err(Error::GameOver), game.claim_draw());
+    // Taking back the mating move makes the game go on
+    game.unmake_move();
+    assert_eq!(None, game.outcome());
+    assert_eq!(GameState::Ongoing, game.state());
+}
+
+#[test]
+fn time_control_parsing() {
+    use clock::{Increment, Period, TimeControl};
+    use std::time::Duration;
+
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    assert_eq!(
+        Ok(TimeControl {
+            periods: vec![
+                Period {
+                    moves: Some(40),
+                    time: minutes(90),
+                    increment: Duration::from_secs(30),
+                    increment_kind: Increment::Fischer,
+                },
+                Period {
+                    moves: None,
+                    time: minutes(30),
+                    increment: Duration::from_secs(30),
+                    increment_kind: Increment::Fischer,
+                },
+            ]
+        }),
+        "40/90+30, then 30+30".parse()
+    );
+    assert_eq!(
+        Ok(TimeControl::sudden_death(minutes(5))),
+        "5".parse::<TimeControl>()
+    );
+    let period: Period = "15d5".parse().unwrap();
+    assert_eq!(Increment::Delay, period.increment_kind);
+    let period: Period = "0.5b2".parse().unwrap();
+    assert_eq!(Increment::Bronstein, period.increment_kind);
+    assert_eq!(Duration::from_secs(30), period.time);
+    assert_eq!(Duration::from_secs(2), period.increment);
+    for s in [
+        "",
+        "x",
+        "0/90",
+        "40/",
+        "5+",
+        "-5",
+        "5+3, then",
+        "1e20",
+        "1e307",
+        "40/1e307+0",
+        "5+1e20",
+        "NaN",
+        "inf",
+    ] {
+        assert_eq!(Err(Error::ParsingError), s.parse::<TimeControl>(), "{}", s);
+    }
+}
+
+#[test]
+fn clock_increments() {
+    use clock::{Clock, ManualTimeSource};
+    use std::{sync::Arc, time::Duration};
+
+    let secs = Duration::from_secs;
+    let time = Arc::new(ManualTimeSource::default());
+    let clock = |control: &str| {
+        let mut clock = Clock::with_time_source(control.parse().unwrap(), time.clone());
+        clock.start(Color::White);
+        clock
+    };
+
+    // Fischer: the increment is always added
+    let mut fischer = clock("1+10");
+    time.advance(secs(3));
+    fischer.press();
+    assert_eq!(secs(67), fischer.remaining(Color::White));
+    assert_eq!(Some(Color::Black), fischer.running());
+
+    // Bronstein: the time used is given back, up to the increment
+    let mut bronstein = clock("1b10");
+    time.advance(secs(3));
+    bronstein.press();
+    assert_eq!(secs(60), bronstein.remaining(Color::White));
+    time.advance(secs(15));
+    bronstein.press();
+    assert_eq!(secs(55), bronstein.remaining(Color::Black));
+
+    // Delay: the clock only counts down after the delay
+    let mut delay = clock("1d10");
+    time.advance(secs(3));
+    assert_eq!(secs(60), delay.remaining(Color::White));
+    time.advance(secs(12));
+    assert_eq!(secs(55), delay.remaining(Color::White));
+    delay.press();
+    assert_eq!(secs(55), delay.remaining(Color::White));
+
+    // The time of the next period is added once its moves are made
+    let mut periods = clock("2/1, then 1/1");
+    for _ in 0..4 {
+        time.advance(secs(10));
+        periods.press();
+    }
+    assert_eq!(secs(60 - 20 + 60), periods.remaining(Color::White));
+    assert_eq!(secs(60 - 20 + 60), periods.remaining(Color::Black));
+    // The last period repeats
+    time.advance(secs(10));
+    periods.press();
+    assert_eq!(secs(150), periods.remaining(Color::White));
+    time.advance(secs(10));
+    periods.press();
+    assert_eq!(secs(150), periods.remaining(Color::Black));
+    time.advance(secs(10));
+    periods.press();
+    assert_eq!(secs(200), periods.remaining(Color::White));
+
+    // Stopped clocks don't run
+    periods.stop();
+    time.advance(secs(1000));
+    assert_eq!(secs(200), periods.remaining(Color::White));
+    assert_eq!(None, periods.flagged());
+}
+
+#[test]
+fn flag_fall() {
+    use clock::{Clock, ManualTimeSource, TimeControl};
+    use std::{sync::Arc, time::Duration};
+
+    let time = Arc::new(ManualTimeSource::default());
+    let control = TimeControl::sudden_death(Duration::from_secs(60));
+    let mut game = Game::new(Board::default());
+    game.set_clock(Clock::with_time_source(control.clone(), time.clone()));
+    assert_eq!(Some(Color::White), game.clock().unwrap().running());
+
+    time.advance(Duration::from_secs(30));
+    game.make_move(Move::arabic("e2e4").unwrap()).unwrap();
+    time.advance(Duration::from_secs(59));
+    assert_eq!(GameState::Ongoing, game.check_flag());
+    time.advance(Duration::from_secs(1));
+    assert_eq!(Some(Color::Black), game.clock().unwrap().flagged());
+    let outcome = Outcome::Decisive {
+        winner: Color::White,
+        reason: WinReason::Timeout,
+    };
+    // The move is too late
+    assert_eq!(
+        Ok(GameState::Over(outcome)),
+        game.make_move(Move::arabic("e7e5").unwrap())
+    );
+    assert_eq!(Some(outcome), game.outcome());
+    assert_eq!(1, game.history().len());
+    assert_eq!(None, game.clock().unwrap().running());
+
+    // A lone king can't win on time
+    let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap());
+    game.set_clock(Clock::with_time_source(control, time.clone()));
+    time.advance(Duration::from_secs(60));
+    assert_eq!(
+        GameState::Over(Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)),
+        game.check_flag()
+    );
+}
+
+#[test]
+fn chess960_start_positions() {
+    let standard = Board::chess960(518).unwrap();
+    assert_eq!(Board::default().tiles(), standard.tiles());
+    assert!(standard.is_chess960());
+    assert!(!Board::default().is_chess960());
+    assert_eq!(
+        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1",
+        Board::chess960(0).unwrap().to_fen()
+    );
+    assert_eq!(None, Board::chess960(960));
+
+    let mut seen = HashSet::new();
+    for index in 0..960 {
+        let board = Board::chess960(index).unwrap();
+        let rank = board.to_fen().split('/').next_back().unwrap()[..8].to_string();
+        let file = |c| rank.find(c).unwrap();
+        let rooks = (file('R'), rank.rfind('R').unwrap());
+        assert!(rooks.0 < file('K') && file('K') < rooks.1, "{}", rank);
+        let bishops = (file('B'), rank.rfind('B').unwrap());
+        assert_ne!(bishops.0 % 2, bishops.1 % 2, "{}", rank);
+        assert!(seen.insert(rank));
+    }
+}
+
+#[test]
+fn chess960_perft() {
+    for (fen, nodes) in [
+        (
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            [21, 528, 12189],
+        ),
+        (
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            [21, 807, 18002],
+        ),
+        (
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            [20, 479, 10471],
+        ),
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        assert!(board.is_chess960());
+        for (depth, &expected) in nodes.iter().enumerate() {
+            assert_eq!(expected, board.perft(depth as u8 + 1), "{}", fen);
         }
+    }
+}
+
+#[test]
+fn chess960_castling() {
+    // The king moves onto the tile the rook leaves
+    let mut game = Game::new(Board::from_fen("k7/8/8/8/8/8/8/5KR1 w G - 0 1").unwrap());
+    assert_eq!("k7/8/8/8/8/8/8/5KR1 w K - 0 1", game.board().to_fen());
+    let castle = Move::arabic("f1g1").unwrap();
+    assert_eq!(Some("O-O".to_string()), castle.as_algebraic(game.board()));
+    assert_eq!(castle, game.board().display_castling(castle));
+    game.make_move(castle).unwrap();
+    assert_eq!("k7/8/8/8/8/8/8/5RK1 b - - 1 1", game.board().to_fen());
+    game.unmake_move();
+    assert_eq!("k7/8/8/8/8/8/8/5KR1 w K - 0 1", game.board().to_fen());
+    assert_eq!(Ok(castle), game.parse_san("O-O"));
+
+    // The rook shields the king's destination until it moves
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/rR1K4 w B - 0 1").unwrap();
+    assert!(board.can_castle_queenside(Color::White));
+    assert!(!board.legal_moves().any(|m| m.to == "b1".parse().unwrap()));
+
+    // Rooks that are not the outermost one are named by file
+    let fen = "4k3/8/8/8/8/8/8/R1R1K2R w KC - 0 1";
+    let board = Board::from_fen(fen).unwrap();
+    assert_eq!(fen, board.to_fen());
+    assert_eq!(
+        Board::from_fen("4k3/8/8/8/8/8/8/R1R1K2R w HC - 0 1").unwrap(),
+        board
+    );
+    assert_eq!(
+        Err(Error::FenError(error::FenError::Castling {
+            offset: 23,
+            reason: "no rook to castle with"
+        })),
+        Board::from_fen("4k3/8/8/8/8/8/8/4K2R w Q - 0 1")
+    );
+
+    // Naming the rooks by file doesn't make the standard setup Chess960
+    let board =
+        Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1").unwrap();
+    assert!(!board.is_chess960());
+    assert_eq!(Board::default(), board);
+    assert_eq!(Board::default().to_fen(), board.to_fen());
+
+    // In standard chess, castling can be written either way
+    let mut game = Game::new(Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap());
+    game.make_move(Move::arabic("e1g1").unwrap()).unwrap();
+    game.make_move(Move::arabic("e8a8").unwrap()).unwrap();
+    assert_eq!("2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2", game.board().to_fen());
+    assert_eq!(
+        &[Move::arabic("e1h1").unwrap(), Move::arabic("e8a8").unwrap()],
+        game.history()
     );
+    let board = game.initial_board();
+    assert_eq!(
+        Move::arabic("e1g1").unwrap(),
+        board.display_castling(game.history()[0])
+    );
+}
+
+#[test]
+fn fen_validation() {
+    use error::FenError::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    for (fen, expected) in [
+        (
+            "",
+            Pieces {
+                offset: 0,
+                reason: "missing field",
+            },
+        ),
+        (
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Pieces {
+                offset: 18,
+                reason: "unknown piece",
+            },
+        ),
+        (
+            "kqqqqqqqq/8/8/8/8/8/8/7K w - - 0 1",
+            Pieces {
+                offset: 8,
+                reason: "too many tiles in rank",
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/8/K6 w - - 0 1",
+            Pieces {
+                offset: 17,
+                reason: "too few tiles in rank",
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/K7 w - - 0 1",
+            Pieces {
+                offset: 15,
+                reason: "too few ranks",
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/8/K7/8 w - - 0 1",
+            Pieces {
+                offset: 17,
+                reason: "too many ranks",
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/8/K34 w - - 0 1",
+            Pieces {
+                offset: 17,
+                reason: "empty tiles not counted with a single digit",
+            },
+        ),
+        (
+            "k6P/8/8/8/8/8/8/K7 w - - 0 1",
+            Pieces {
+                offset: 2,
+                reason: "pawn on the first or last rank",
+            },
+        ),
+        (
+            "k6k/8/8/8/8/8/8/K7 w - - 0 1",
+            Pieces {
+                offset: 2,
+                reason: "more than one king of the same color",
+            },
+        ),
+        (
+            "8/8/8/8/8/8/8/K7 w - - 0 1",
+            Pieces {
+                offset: 0,
+                reason: "missing black king",
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/8/K7 x - - 0 1",
+            NextToMove {
+                offset: 18,
+                reason: "expected \"w\" or \"b\"",
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/8/R6K w - - 0 1",
+            NextToMove {
+                offset: 19,
+                reason: "the player not to move is in check",
+            },
+        ),
+        (
+            "r3k2r/8/8/8/8/8/8/R3K2R w KKkq - 0 1",
+            Castling {
+                offset: 27,
+                reason: "castling right given twice",
+            },
+        ),
+        (
+            "r3k2r/8/8/8/8/8/4K3/R6R w Q - 0 1",
+            Castling {
+                offset: 26,
+                reason: "king not on its home rank",
+            },
+        ),
+        (
+            "r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 1",
+            Castling {
+                offset: 27,
+                reason: "unknown castling right",
+            },
+        ),
+        (
+            "4k3/8/8/8/4P3/8/8/4K3 b - e6 0 1",
+            EnPassant {
+                offset: 27,
+                reason: "en passant tile on the wrong rank",
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 b - e3 0 1",
+            EnPassant {
+                offset: 24,
+                reason: "no pawn can just have moved past the tile",
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 b - e9 0 1",
+            EnPassant {
+                offset: 24,
+                reason: "not a tile",
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 b - - x 1",
+            HalfmoveCounter {
+                offset: 26,
+                reason: "not a number",
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 b - - 0 0",
+            MoveNumber {
+                offset: 28,
+                reason: "must be at least 1",
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 b - - 0",
+            MoveNumber {
+                offset: 27,
+                reason: "missing field",
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 b - - 0 1 extra",
+            TrailingInput {
+                offset: 30,
+                reason: "unexpected input after the move number",
+            },
+        ),
+    ] {
+        assert_eq!(
+            Err(Error::FenError(expected)),
+            Board::from_fen(fen),
+            "{}",
+            fen
+        );
+    }
+    assert!(Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+    assert!(Board::from_fen(START).is_ok());
+
+    // Nothing may panic, and whatever is accepted must survive a round trip
+    for fen in [
+        START,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 2",
+    ] {
+        let chars = fen.chars().collect::<Vec<_>>();
+        for i in 0..=chars.len() {
+            let truncated = chars[..i].iter().collect::<String>();
+            let _ = Board::from_fen(&truncated);
+            for c in "/0189pPkKrRqQ-abhwx é".chars() {
+                let mut mutated = chars.clone();
+                if i < mutated.len() {
+                    mutated[i] = c;
+                } else {
+                    mutated.push(c);
+                }
+                let mutated = mutated.into_iter().collect::<String>();
+                if let Ok(board) = Board::from_fen(&mutated) {
+                    assert_eq!(
+                        board,
+                        Board::from_fen(&board.to_fen()).unwrap(),
+                        "{}",
+                        mutated
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn failed_moves_leave_game_untouched() {
+    let mut game = Game::new(Board::from_fen("4k3/1P6/8/8/8/8/8/R3K3 w Q - 0 1").unwrap());
+    game.offer_draw(Color::Black).unwrap();
+    let board = game.board().clone();
+    let promote = |kind| {
+        let mut m = Move::arabic("b7b8").unwrap();
+        m.promotion = Some(kind);
+        m
+    };
+    for (expected, m) in [
+        (Error::NoPieceToMove, Move::arabic("d4d5").unwrap()),
+        (Error::OtherPlayersTurn, Move::arabic("e8e7").unwrap()),
+        (Error::IllegalMove, Move::arabic("a1b2").unwrap()),
+        (Error::IllegalMove, Move::arabic("e1g1").unwrap()),
+        (Error::RequiresPromotion, Move::arabic("b7b8").unwrap()),
+        (Error::IllegalMove, promote(piece::Kind::King)),
+        (Error::IllegalMove, promote(piece::Kind::Pawn)),
+    ] {
+        assert_eq!(Err(expected), game.make_move(m), "{}", m.as_arabic());
+        assert_eq!(&board, game.board(), "{}", m.as_arabic());
+        assert!(game.history().is_empty());
+        assert_eq!(1, game.position_keys().len());
+        assert_eq!(Some(Color::Black), game.draw_offer());
+    }
+
+    // The promotion of moves that don't need one is ignored
+    let mut m = Move::arabic("e1d1").unwrap();
+    m.promotion = Some(piece::Kind::Queen);
+    game.make_move(m).unwrap();
+    assert_eq!(Move::arabic("e1d1").unwrap(), game.history()[0]);
+    assert_eq!(None, game.draw_offer());
+
+    game.resign(Color::Black).unwrap();
+    let board = game.board().clone();
+    assert_eq!(
+        Err(Error::GameOver),
+        game.make_move(Move::arabic("e8e7").unwrap())
+    );
+    assert_eq!(&board, game.board());
+    assert_eq!(1, game.history().len());
+}
+
+#[test]
+fn illegal_move_explanations() {
+    use error::IllegalMoveReason::*;
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    let tiles = |tiles: &[&str]| {
+        tiles
+            .iter()
+            .map(|t| Position::from_str(t).unwrap())
+            .fold(Bitboard::EMPTY, |acc, t| acc | t.into())
+    };
+    let tile = |t: &str| Position::from_str(t).unwrap();
+    for (fen, m, expected) in [
+        (START_FEN, "b1b3", Unreachable),
+        (START_FEN, "a1a2", OwnPiece),
+        (START_FEN, "a1a5", Blocked { by: tile("a2") }),
+        (START_FEN, "f1c4", Blocked { by: tile("e2") }),
+        (START_FEN, "e2d3", NothingToCapture),
+        (START_FEN, "e1g1", Blocked { by: tile("f1") }),
+        (START_FEN, "e2e5", Unreachable),
+        (
+            "4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1",
+            "e2e4",
+            Blocked { by: tile("e3") },
+        ),
+        (
+            "4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1",
+            "e2d3",
+            Pinned { pinner: tile("e7") },
+        ),
+        (
+            "4k3/8/8/8/8/8/3P4/r3K3 w - - 0 1",
+            "d2d3",
+            InCheck {
+                checkers: tiles(&["a1"]),
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/r7/4K3 w - - 0 1",
+            "e1e2",
+            IntoCheck {
+                attackers: tiles(&["a2"]),
+            },
+        ),
+        (
+            "r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1",
+            "e1g1",
+            NoCastlingRight,
+        ),
+        (
+            "4k3/8/8/8/8/8/4r3/4K2R w K - 0 1",
+            "e1g1",
+            InCheck {
+                checkers: tiles(&["e2"]),
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/5r2/4K2R w K - 0 1",
+            "e1h1",
+            CastlingThroughCheck {
+                tile: tile("f1"),
+                attackers: tiles(&["f2"]),
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/6r1/4K2R w K - 0 1",
+            "e1g1",
+            CastlingThroughCheck {
+                tile: tile("g1"),
+                attackers: tiles(&["g2"]),
+            },
+        ),
+        (
+            "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+            "e5d6",
+            Pinned { pinner: tile("h5") },
+        ),
+        (
+            "4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1",
+            "d2f3",
+            Pinned { pinner: tile("b4") },
+        ),
+    ] {
+        let game = Game::new(Board::from_fen(fen).unwrap());
+        let m = Move::arabic(m).unwrap();
+        assert_eq!(
+            Some(expected),
+            game.explain_illegal(m),
+            "{} {}",
+            fen,
+            m.as_arabic()
+        );
+        assert_eq!(Err(Error::IllegalMove), game.clone().make_move(m));
+    }
+
+    let mut promotion = Move::arabic("b7b8").unwrap();
+    promotion.promotion = Some(piece::Kind::King);
+    let game = Game::new(Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+    assert_eq!(Some(InvalidPromotion), game.explain_illegal(promotion));
+    assert_eq!(None, game.explain_illegal(Move::arabic("b7b8q").unwrap()));
+    assert_eq!(None, game.explain_illegal(Move::arabic("e8e7").unwrap()));
+
+    // Exactly the moves that aren't legal are explained
+    for fen in [
+        START_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        let mut boards = vec![board.clone()];
+        for m in board.legal_moves() {
+            let mut after = board.clone();
+            after.make_move_unchecked(m);
+            boards.push(after);
+        }
+        for board in boards {
+            let legal = board.legal_moves().collect::<HashSet<_>>();
+            for from in board.by_color(board.next_to_move()) {
+                for to in Bitboard::FULL {
+                    let m = Move::from((from, to));
+                    let explanation = board.explain_illegal(m);
+                    assert_eq!(
+                        legal.contains(&board.normalize_castling(m)),
+                        explanation.is_none(),
+                        "{} {} {:?}",
+                        board.to_fen(),
+                        m.as_arabic(),
+                        explanation
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn checks_and_pins() {
+    let tiles = |tiles: &[&str]| {
+        tiles
+            .iter()
+            .map(|t| Position::from_str(t).unwrap())
+            .fold(Bitboard::EMPTY, |acc, t| acc | t.into())
+    };
+    let tile = |t: &str| Position::from_str(t).unwrap();
+
+    let board = Board::default();
+    assert!(!board.is_check());
+    assert_eq!(Bitboard::EMPTY, board.checkers());
+    assert!(board.pinned_pieces(Color::White).is_empty());
+    assert_eq!(
+        tiles(&["b1", "b2", "d2"]),
+        board.attackers_of(tile("c3"), Color::White)
+    );
+    assert_eq!(
+        Bitboard::EMPTY,
+        board.attackers_of(tile("c3"), Color::Black)
+    );
+
+    // Moving the bishop discovers a check by the rook, and gives check itself
+    let mut game = Game::new(Board::from_fen("4k3/8/8/8/4B3/8/8/4R1K1 w - - 0 1").unwrap());
+    // The bishop blocks the rook, but is not pinned since it's white
+    assert!(game.board().pinned_pieces(Color::White).is_empty());
+    assert!(game.board().pinned_pieces(Color::Black).is_empty());
+    game.make_move(Move::arabic("e4c6").unwrap()).unwrap();
+    assert!(game.board().is_check());
+    assert_eq!(tiles(&["c6", "e1"]), game.board().checkers());
+
+    // Pieces of the other color, or with another piece next to them, are
+    // not pinned
+    let board = Board::from_fen("r1k5/8/p7/4q3/8/2N5/P7/K3R2r w - - 0 1").unwrap();
+    assert!(!board.is_check());
+    assert_eq!(
+        vec![
+            Pin {
+                pinned: tile("c3"),
+                pinner: tile("e5"),
+                line: tiles(&["b2", "c3", "d4", "e5"]),
+            },
+            Pin {
+                pinned: tile("e1"),
+                pinner: tile("h1"),
+                line: tiles(&["b1", "c1", "d1", "e1", "f1", "g1", "h1"]),
+            },
+        ],
+        board.pinned_pieces(Color::White)
+    );
+    assert!(board.pinned_pieces(Color::Black).is_empty());
+    assert_eq!(
+        tiles(&["c3", "e1"]),
+        board.attackers_of(tile("e4"), Color::White)
+    );
+    assert_eq!(tiles(&["e5"]), board.attackers_of(tile("e4"), Color::Black));
+}
+
+#[test]
+fn attack_and_control_maps() {
+    let tile = |t: &str| Position::from_str(t).unwrap();
+
+    let board = Board::default();
+    let white = board.attack_map(Color::White);
+    assert_eq!(3, white.count(tile("c3")));
+    assert_eq!(4, white.count(tile("d2")));
+    assert_eq!(0, white.count(tile("e4")));
+    assert_eq!(
+        Bitboard(0xffff_ff00_0000_0000) ^ tile("a1").into() ^ tile("h1").into(),
+        white.attacked()
+    );
+    let control = board.control_map();
+    assert_eq!([0, 1, 1, 1, 1, 1, 1, 0], control[7]);
+    assert_eq!([-2, -2, -3, -2, -2, -3, -2, -2], control[2]);
+    assert_eq!([0; 8], control[4]);
+
+    // White attacks d5 twice and black once, since the rook behind the
+    // queen does not count until the queen has moved
+    let board = Board::from_fen("3r2k1/8/2n5/3p4/8/2N5/3Q4/3R2K1 w - - 0 1").unwrap();
+    let white = board.attack_map(Color::White);
+    let black = board.attack_map(Color::Black);
+    assert_eq!(
+        Bitboard::from(tile("c3")) | tile("d2").into(),
+        white[tile("d5")]
+    );
+    assert_eq!(Bitboard::from(tile("d8")), black[tile("d5")]);
+    assert_eq!(1, board.control_map()[3][3]);
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        for color in [Color::White, Color::Black] {
+            let map = board.attack_map(color);
+            for pos in Bitboard::FULL {
+                assert_eq!(board.attackers_of(pos, color), map[pos]);
+            }
+        }
+    }
+}
+
+#[test]
+fn static_exchange_evaluation() {
+    for (fen, m, expected) in [
+        // Undefended and defended pawns
+        ("4k3/8/8/4p3/8/5N2/8/4K3 w - - 0 1", "f3e5", 100),
+        ("4k3/8/3p4/4p3/8/5N2/8/4K3 w - - 0 1", "f3e5", 100 - 320),
+        // The second rook joins in once the first one has taken
+        ("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5", 100),
+        ("3r2k1/3r4/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "d2d5", 100 - 500),
+        // The king may only take back if nothing can take it
+        ("8/8/8/3k4/4p3/8/8/4R1K1 w - - 0 1", "e1e4", 100 - 500),
+        ("8/8/8/3k4/4p3/8/6B1/4R1K1 w - - 0 1", "e1e4", 100),
+        // Promotions, with and without capturing
+        ("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q", 500 + 800),
+        (
+            "1r2k3/P2n4/8/8/8/8/8/4K3 w - - 0 1",
+            "a7b8q",
+            500 + 800 - 900,
+        ),
+        (
+            "1r2k3/P2n4/8/8/8/8/8/4K3 w - - 0 1",
+            "a7b8n",
+            500 + 220 - 320,
+        ),
+        ("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", -100),
+        // Black pawns take back when promoting as well
+        ("4k3/8/8/8/8/8/p7/1R2K3 b - - 0 1", "a2b1q", 500 + 800),
+        (
+            "4k3/8/8/8/8/8/p7/1R1QK3 b - - 0 1",
+            "a2b1q",
+            500 + 800 - 900,
+        ),
+        // En passant, castling and quiet moves
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+        ("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", 0),
+        ("4k3/8/8/8/3p4/8/8/1N2K3 w - - 0 1", "b1c3", -320),
+        ("4k3/8/8/8/3p4/8/8/1N2K3 w - - 0 1", "b1a3", 0),
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(
+            expected,
+            board.see(Move::arabic(m).unwrap()),
+            "{} {}",
+            fen,
+            m
+        );
+    }
 }
@@ -0,0 +1,176 @@
+use std::mem;
+
+use crate::Move;
+
+/// Size of a table when none is given, in megabytes
+pub const DEFAULT_MEGABYTES: usize = 16;
+
+/// A fixed-size hash table keyed by `Board::zobrist`, remembering results
+/// for positions that have been seen before.
+///
+/// Each key maps to a single slot, so entries for different positions can
+/// push each other out. `E` is what is stored per position: an `Entry` for
+/// search, or a `PerftEntry` for counting moves with `Board::perft_cached`.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable<E = Entry> {
+    slots: Vec<Option<(u64, E)>>,
+    /// Incremented for every new search, so that entries from earlier ones
+    /// can be recognized and replaced first
+    generation: u8,
+}
+
+/// Which side of the true score the score of an `Entry` is on, since it may
+/// come from a search that was cut off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The true score is at least this high
+    Lower,
+    /// The true score is at most this high
+    Upper,
+}
+
+/// The result of searching a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// Remaining depth the position was searched to
+    pub depth: u8,
+    pub bound: Bound,
+    /// Score from the perspective of the player to move. Mate scores are
+    /// stored relative to this position rather than the root of the search.
+    pub score: i32,
+    pub best_move: Option<Move>,
+    pub(crate) generation: u8,
+}
+
+/// The number of leaf nodes below a position at some depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerftEntry {
+    pub depth: u8,
+    pub nodes: u64,
+}
+
+impl<E: Copy> TranspositionTable<E> {
+    /// Creates a table taking up about `megabytes` of memory, but always with
+    /// room for at least one entry.
+    pub fn new(megabytes: usize) -> Self {
+        let len = megabytes * 1024 * 1024 / mem::size_of::<Option<(u64, E)>>();
+        Self {
+            slots: vec![None; len.max(1)],
+            generation: 0,
+        }
+    }
+    /// Number of entries the table has room for
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+    /// Returns how full the table is in permille, as reported by the UCI
+    /// `hashfull` info.
+    pub fn hashfull(&self) -> usize {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        sample.iter().filter(|slot| slot.is_some()).count() * 1000 / sample.len()
+    }
+    fn index(&self, key: u64) -> usize {
+        // Maps the key onto the slots evenly without a division
+        ((key as u128 * self.slots.len() as u128) >> 64) as usize
+    }
+    /// Returns the entry for the position with `key`, if it's in the table.
+    pub fn get(&self, key: u64) -> Option<E> {
+        match self.slots[self.index(key)] {
+            Some((k, entry)) if k == key => Some(entry),
+            _ => None,
+        }
+    }
+    /// Stores `entry` for the position with `key`, unless the slot is taken
+    /// by another entry for which `keep` returns true.
+    fn insert_unless(&mut self, key: u64, entry: E, keep: impl FnOnce(&E) -> bool) {
+        let index = self.index(key);
+        let slot = &mut self.slots[index];
+        if let Some((k, old)) = slot {
+            if *k != key && keep(old) {
+                return;
+            }
+        }
+        *slot = Some((key, entry));
+    }
+}
+
+impl Default for TranspositionTable<Entry> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEGABYTES)
+    }
+}
+
+/// Scores beyond this are mates, which depend on the distance from the root
+/// and have to be adjusted when stored and retrieved.
+const MATE_BOUND: i32 = crate::search::MATE_BOUND;
+
+impl TranspositionTable<Entry> {
+    /// Marks the start of a new search, making the entries of earlier ones
+    /// the first to be replaced.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+    /// Returns the entry for the position with `key`, found `ply` plies from
+    /// the root of the search.
+    pub(crate) fn probe(&self, key: u64, ply: usize) -> Option<Entry> {
+        let mut entry = self.get(key)?;
+        if entry.score > MATE_BOUND {
+            entry.score -= ply as i32;
+        } else if entry.score < -MATE_BOUND {
+            entry.score += ply as i32;
+        }
+        Some(entry)
+    }
+    /// Stores the result of searching the position with `key`, found `ply`
+    /// plies from the root. Entries from the current search are only
+    /// replaced by ones searched at least as deep.
+    pub(crate) fn store(
+        &mut self,
+        key: u64,
+        ply: usize,
+        depth: u8,
+        bound: Bound,
+        score: i32,
+        best_move: Option<Move>,
+    ) {
+        let score = if score > MATE_BOUND {
+            score + ply as i32
+        } else if score < -MATE_BOUND {
+            score - ply as i32
+        } else {
+            score
+        };
+        let generation = self.generation;
+        let entry = Entry {
+            depth,
+            bound,
+            score,
+            best_move,
+            generation,
+        };
+        self.insert_unless(key, entry, |old| {
+            old.generation == generation && old.depth > depth
+        });
+    }
+}
+
+impl TranspositionTable<PerftEntry> {
+    /// Returns the number of leaf nodes at `depth` below the position with
+    /// `key`, if known.
+    pub(crate) fn nodes(&self, key: u64, depth: u8) -> Option<u64> {
+        self.get(key)
+            .filter(|entry| entry.depth == depth)
+            .map(|entry| entry.nodes)
+    }
+    /// Remembers the leaf node count, preferring deeper counts since they
+    /// save more work.
+    pub(crate) fn store_nodes(&mut self, key: u64, depth: u8, nodes: u64) {
+        self.insert_unless(key, PerftEntry { depth, nodes }, |old| old.depth > depth);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fmt, str::FromStr};
 
-use crate::{piece, Board, Error};
+use crate::{piece, Error};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Move {
@@ -34,6 +34,9 @@ impl Move {
     ///
     /// If `s` is not valid arabic notation, `Err(Error::ParsingError)` is returned.
     pub fn arabic(s: &str) -> Result<Self, Error> {
+        if !s.is_ascii() {
+            return Err(Error::ParsingError);
+        }
         match s.len() {
             0..=3 => Err(Error::ParsingError),
             4 => Ok(Self {
@@ -46,81 +49,24 @@ impl Move {
                 to: s[2..4].parse()?,
                 promotion: Some(s[4..5].parse()?),
             }),
-            _ => unreachable!(),
         }
     }
-    /// Returns the move as a string in arabic notation, e.g. "h8a1"
+    /// Returns the move as a string in arabic notation, e.g. "h8a1" or
+    /// "e7e8q" for promotions. This is the format used by UCI.
     pub fn as_arabic(&self) -> String {
-        format!("{}{}", self.from, self.to)
-    }
-    /// # Note
-    /// Doesn't work
-    /// # If it worked:
-    /// Returns the move as a string in algebraic notation, e.g. "Qh4xe1"
-    /// `board` must be the state of the board *before* the move is made.
-    ///
-    /// For moves that lead to pawn promotions, `promotion` must be set to the
-    /// kind of piece the pawn was turned in to. If not, None will be returned.
-    /// For all other moves, it is not used and should be set to None.
-    ///
-    /// If no piece exists on the `move`'s `from` tile, None is returned.
-    pub fn as_algebraic(&self, board: &Board, promotion: Option<piece::Kind>) -> Option<String> {
-        let piece = board[self.from]?;
-
-        if piece.kind == piece::Kind::King && self.to.file() == self.from.file() + 2 {
-            // Kingside castling
-            return Some("0-0".to_owned());
-        } else if piece.kind == piece::Kind::King && self.to.file() == self.to.file() - 2 {
-            // Queenside castling
-            return Some("0-0-0".to_owned());
+        match self.promotion {
+            Some(kind) => format!(
+                "{}{}{}",
+                self.from,
+                self.to,
+                kind.name().to_ascii_lowercase()
+            ),
+            None => format!("{}{}", self.from, self.to),
         }
-
-        let kind_part = if piece.kind == piece::Kind::Pawn {
-            "".to_owned()
-        } else {
-            piece.kind.name().to_string()
-        };
-        let from_file_part = if (0..8u8)
-            .map(|rank| Position::new_unchecked(self.from.file(), rank))
-            .map(|pos| board[pos] == Some(piece))
-            .any(|b| b)
-        {
-            ((self.from.file() + b'a') as char).to_string()
-        } else {
-            "".to_owned()
-        };
-        let from_rank_part = if (0..8u8)
-            .map(|file| Position::new_unchecked(file, self.from.rank()))
-            .map(|pos| board[pos] == Some(piece))
-            .any(|b| b)
-        {
-            ((b'8' - self.from.rank()) as char).to_string()
-        } else {
-            "".to_owned()
-        };
-        let capture_part = if board[self.to].is_some() { "x" } else { "" };
-        let dest_part = self.to;
-        let promotion_part = promotion.map_or("".to_owned(), |kind| kind.name().to_string());
-        let check_part = if piece.checks(self.to, board) {
-            "+"
-        } else {
-            ""
-        };
-
-        Some(format!(
-            "{kind}{from_file}{from_rank}{capture}{destination}{promotion}{check}",
-            kind = kind_part,
-            from_file = from_file_part,
-            from_rank = from_rank_part,
-            capture = capture_part,
-            destination = dest_part,
-            promotion = promotion_part,
-            check = check_part,
-        ))
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Color {
     White,
     Black,
@@ -171,7 +117,7 @@ impl Position {
         }
     }
     pub fn new_i8(file: i8, rank: i8) -> Option<Self> {
-        if 0 <= file && file < 8 && 0 <= rank && rank < 8 {
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
             Some(Self {
                 file: file as u8,
                 rank: rank as u8,
@@ -195,6 +141,17 @@ impl Position {
     pub fn rank(&self) -> u8 {
         self.rank
     }
+    /// Index of the tile when the board is laid out row by row starting at
+    /// a8, i.e. `rank * 8 + file`. This is the bit used in a `Bitboard`.
+    pub fn index(&self) -> u8 {
+        self.rank * 8 + self.file
+    }
+    pub(crate) fn from_index(index: u8) -> Self {
+        Self {
+            file: index % 8,
+            rank: index / 8,
+        }
+    }
     pub fn get_file(c: char) -> u8 {
         c as u8 + b'a'
     }
@@ -216,7 +173,7 @@ impl FromStr for Position {
         if s.len() > 2 {
             return Err(Error::ParsingError);
         }
-        let file = match s.get(0) {
+        let file = match s.first() {
             c @ Some(b'a'..=b'h') => c.unwrap() - b'a',
             _ => return Err(Error::ParsingError),
         };
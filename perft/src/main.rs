@@ -0,0 +1,178 @@
+//! Counts the leaf nodes of the tree of legal moves from a position, for
+//! finding bugs in move generation by comparing the counts with those of
+//! other engines.
+//!
+//! `perft <depth> [<fen>] [moves <move>...]` prints the count for each legal
+//! move in UCI notation, followed by the total. Playing the move with a wrong
+//! count and running again one ply shallower narrows down the bug.
+//!
+//! `perft --suite <file> [<max depth>]` checks the counts given in an EPD
+//! file, with lines like `<fen> ;D1 20 ;D2 400`.
+//!
+//! With `--hash <megabytes>` before the other arguments, counts of positions
+//! reached through transpositions are remembered.
+
+use chess_engine::{
+    tt::{PerftEntry, TranspositionTable},
+    Board, Error, Game, Move,
+};
+use std::{
+    env, fs, process,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "usage: perft [--hash <megabytes>] <depth> [<fen>] [moves <move>...]
+       perft [--hash <megabytes>] --suite <file> [<max depth>]";
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let mut table = None;
+    if args.first().map(String::as_str) == Some("--hash") {
+        match args.get(1).and_then(|mb| mb.parse().ok()) {
+            Some(mb) => table = Some(TranspositionTable::new(mb)),
+            None => usage(),
+        }
+        args.drain(..2);
+    }
+
+    let result = match args.first().map(String::as_str) {
+        Some("--suite") => match (args.get(1), args.get(2).map(|d| d.parse())) {
+            (Some(path), None) => suite(path, u8::MAX, table.as_mut()),
+            (Some(path), Some(Ok(max_depth))) => suite(path, max_depth, table.as_mut()),
+            _ => usage(),
+        },
+        Some(depth) => match depth.parse() {
+            Ok(depth) => divide(depth, &args[1..], table.as_mut()).map_err(|err| err.to_string()),
+            Err(_) => usage(),
+        },
+        None => usage(),
+    };
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn count(board: &Board, depth: u8, table: Option<&mut TranspositionTable<PerftEntry>>) -> u64 {
+    match table {
+        Some(table) => board.perft_cached(depth, table),
+        None => board.perft(depth),
+    }
+}
+
+fn nodes_per_second(nodes: u64, time: Duration) -> u64 {
+    (nodes as f64 / time.as_secs_f64().max(1e-9)) as u64
+}
+
+/// Prints the counts for each move from the position given by `args`, i.e.
+/// an optional FEN followed by optional moves.
+fn divide(
+    depth: u8,
+    args: &[String],
+    table: Option<&mut TranspositionTable<PerftEntry>>,
+) -> Result<bool, Error> {
+    let moves_start = args.iter().position(|a| a == "moves");
+    let (fen, moves) = args.split_at(moves_start.unwrap_or(args.len()));
+    let board = if fen.is_empty() {
+        Board::default()
+    } else {
+        Board::from_fen(&fen.join(" "))?
+    };
+    let mut game = Game::new(board);
+    for m in moves.iter().skip(1) {
+        game.make_move(Move::arabic(m)?)?;
+    }
+    let board = game.board();
+
+    let start = Instant::now();
+    let mut counts = match table {
+        Some(table) => board.divide_cached(depth, table),
+        None => board.divide(depth),
+    }
+    .into_iter()
+    .map(|(m, nodes)| (board.display_castling(m).as_arabic(), nodes))
+    .collect::<Vec<_>>();
+    let time = start.elapsed();
+    counts.sort();
+
+    for (m, nodes) in &counts {
+        println!("{}: {}", m, nodes);
+    }
+    let nodes = if depth == 0 {
+        1
+    } else {
+        counts.iter().map(|(_, nodes)| nodes).sum()
+    };
+    println!();
+    println!("Moves: {}", counts.len());
+    println!("Nodes: {}", nodes);
+    println!("Time: {} ms", time.as_millis());
+    println!("Nodes/second: {}", nodes_per_second(nodes, time));
+    Ok(true)
+}
+
+/// Checks all counts up to `max_depth` in the EPD file at `path`, returning
+/// whether they were all correct.
+fn suite(
+    path: &str,
+    max_depth: u8,
+    mut table: Option<&mut TranspositionTable<PerftEntry>>,
+) -> Result<bool, String> {
+    let epd = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    let (mut passed, mut failed, mut total_nodes) = (0, 0, 0);
+    let start = Instant::now();
+
+    for (i, line) in epd.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason: &str| format!("{}:{}: {}", path, i + 1, reason);
+        let mut fields = line.split(';');
+        let mut fen = fields.next().unwrap().trim().to_string();
+        // EPD leaves out the move counters
+        if fen.split_ascii_whitespace().count() == 4 {
+            fen.push_str(" 0 1");
+        }
+        let board = Board::from_fen(&fen).map_err(|err| error(&err.to_string()))?;
+        println!("{}", fen);
+
+        for field in fields {
+            let (depth, expected) = field
+                .trim()
+                .strip_prefix('D')
+                .and_then(|f| f.split_once(' '))
+                .and_then(|(d, n)| Some((d.parse::<u8>().ok()?, n.trim().parse::<u64>().ok()?)))
+                .ok_or_else(|| error(&format!("invalid count \"{}\"", field.trim())))?;
+            if depth > max_depth {
+                continue;
+            }
+            let nodes = count(&board, depth, table.as_deref_mut());
+            total_nodes += nodes;
+            if nodes == expected {
+                passed += 1;
+                println!("  depth {}: {} ok", depth, nodes);
+            } else {
+                failed += 1;
+                println!("  depth {}: {} FAILED, expected {}", depth, nodes, expected);
+            }
+        }
+    }
+
+    let time = start.elapsed();
+    println!();
+    println!("{} passed, {} failed", passed, failed);
+    println!("Nodes: {}", total_nodes);
+    println!("Time: {} ms", time.as_millis());
+    println!("Nodes/second: {}", nodes_per_second(total_nodes, time));
+    Ok(failed == 0)
+}
@@ -1,20 +1,49 @@
-use chess_engine::{piece, Board, Game, GameState, Move, Position};
+use chess_engine::{piece, Board, Error, Game, GameState, Limits, Move, Pgn, Position, Searcher};
 use std::{
     io::{BufRead, Write},
     str::FromStr,
+    time::Duration,
 };
 
+/// What a line that isn't one of the commands refers to.
+#[derive(Debug, PartialEq, Eq)]
+enum Input {
+    Move(Move),
+    /// A tile with a piece on it, whose moves should be shown
+    Tile(Position),
+}
+
+/// Reads a move in UCI notation or SAN, or else a tile. Moves come first, so
+/// that pawn moves like "e4" aren't taken for tiles.
+fn parse_input(game: &Game, line: &str) -> Result<Input, Error> {
+    match Move::arabic(line).or_else(|_| game.parse_san(line)) {
+        Ok(m) => Ok(Input::Move(m)),
+        Err(err) => match Position::from_str(line) {
+            Ok(pos) if game.board()[pos].is_some() => Ok(Input::Tile(pos)),
+            _ => Err(err),
+        },
+    }
+}
+
 fn main() {
     let stdin = std::io::stdin();
 
     let mut fen = String::new();
     let board = loop {
-        print!("Initial board state (empty for none) > ");
+        print!("Initial board state (FEN, \"960 <n>\" for Chess960, empty for none) > ");
         std::io::stdout().lock().flush().unwrap();
         fen.clear();
         stdin.read_line(&mut fen).unwrap();
-        break if fen == "" {
+        break if fen.trim().is_empty() {
             Board::default()
+        } else if let Some(index) = fen.trim().strip_prefix("960 ") {
+            match index.trim().parse().ok().and_then(Board::chess960) {
+                Some(board) => board,
+                None => {
+                    println!("Chess960 positions are numbered 0 to 959");
+                    continue;
+                }
+            }
         } else {
             match Board::from_fen(&fen) {
                 Ok(board) => board,
@@ -27,35 +56,114 @@ fn main() {
     };
 
     let mut game = Game::new(board);
-    print!("{}", game.board().to_string());
+    print!("{}", game.board());
     let mut lines = stdin.lock().lines().map(|line| line.unwrap());
     while let Some(line) = lines.next() {
         let line = line.trim();
-        if line.len() == 2 {
-            let pos = match Position::from_str(line) {
-                Ok(pos) => pos,
-                Err(err) => {
-                    println!("{}", err);
-                    continue;
+        if line == "undo" {
+            match game.unmake_move() {
+                Some(m) => println!("Took back {}", game.board().display_castling(m).as_arabic()),
+                None => println!("No moves to take back"),
+            }
+            print!("{}", game.board());
+            continue;
+        }
+        let color = game.board().next_to_move();
+        let ended = match line {
+            "claim" => Some(game.claim_draw()),
+            "resign" => Some(game.resign(color)),
+            "accept" => Some(game.accept_draw(color)),
+            _ => None,
+        };
+        if let Some(ended) = ended {
+            match ended {
+                Ok(_) => {
+                    println!("{}", game.outcome().unwrap());
+                    print!("{}", game.to_pgn());
+                    return;
                 }
-            };
-            match game.board()[pos] {
-                Some(piece) => println!(
-                    "{}",
-                    piece
-                        .moves(game.board(), pos)
-                        .fold(String::new(), |acc, p| format!("{} {}", acc, p))
-                ),
-                None => {}
+                Err(err) => println!("{}", err),
             }
             continue;
         }
-
-        let mut m = match Move::arabic(line.trim()) {
-            Ok(m) => m,
-            Err(err) => {
+        if line == "offer" {
+            match game.offer_draw(color) {
+                Ok(()) => println!("{:?} offers a draw", color),
+                Err(err) => println!("{}", err),
+            }
+            continue;
+        }
+        if line == "decline" {
+            if let Err(err) = game.decline_draw(color) {
                 println!("{}", err);
-                continue;
+            }
+            continue;
+        }
+        if line == "control" {
+            // White attackers minus black attackers of each tile
+            for (i, row) in game.board().control_map().iter().enumerate() {
+                print!("{}", 8 - i);
+                for &control in row {
+                    match control {
+                        0 => print!("  ."),
+                        _ => print!(" {:+}", control),
+                    }
+                }
+                println!();
+            }
+            println!("   A  B  C  D  E  F  G  H");
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("load ") {
+            match std::fs::read_to_string(path.trim()) {
+                Ok(s) => match s.parse::<Pgn>() {
+                    Ok(pgn) => game = pgn.game,
+                    Err(err) => println!("{}", err),
+                },
+                Err(err) => println!("{}", err),
+            }
+            print!("{}", game.board());
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("save ") {
+            if let Err(err) = std::fs::write(path.trim(), game.to_pgn()) {
+                println!("{}", err);
+            }
+            continue;
+        }
+        let mut m = if line == "think" {
+            // Let the computer choose the move
+            let result = Searcher::new(Limits {
+                time: Some(Duration::from_secs(1)),
+                ..Limits::default()
+            })
+            .search(&game);
+            match result.best_move {
+                Some(m) => {
+                    println!("Playing {}", m.as_algebraic(game.board()).unwrap());
+                    m
+                }
+                None => continue,
+            }
+        } else {
+            match parse_input(&game, line) {
+                Ok(Input::Move(m)) => m,
+                Ok(Input::Tile(pos)) => {
+                    let board = game.board();
+                    println!(
+                        "{}",
+                        board[pos]
+                            .unwrap()
+                            .moves(board, pos)
+                            .map(|to| board.display_castling((pos, to).into()).to)
+                            .fold(String::new(), |acc, p| format!("{} {}", acc, p))
+                    );
+                    continue;
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    continue;
+                }
             }
         };
 
@@ -73,19 +181,45 @@ fn main() {
 
         match game.make_move(m) {
             Ok(GameState::Ongoing) => (),
-            Ok(GameState::Draw) => {
-                println!("Draw!");
-                return;
+            Ok(GameState::DrawClaimable(reason)) => {
+                println!("A draw by {} can be claimed with \"claim\"", reason)
             }
-            Ok(GameState::Checkmate { winner }) => {
-                println!("Checkmate! {:?} wins", winner);
+            Ok(GameState::Over(outcome)) => {
+                println!("{}", outcome);
+                print!("{}", game.to_pgn());
                 return;
             }
             Err(err) => {
-                println!("{}", err);
+                match game.explain_illegal(m) {
+                    Some(reason) => println!("{}: {}", err, reason),
+                    None => println!("{}", err),
+                }
                 continue;
             }
         };
-        print!("{}", game.board().to_string());
+        print!("{}", game.board());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pawn_moves_are_not_tiles() {
+        let mut game = Game::new(Board::default());
+        let e4 = Move::arabic("e2e4").unwrap();
+        assert_eq!(Ok(Input::Move(e4)), parse_input(&game, "e4"));
+        assert_eq!(
+            Ok(Input::Tile("e2".parse().unwrap())),
+            parse_input(&game, "e2")
+        );
+        assert_eq!(Err(Error::IllegalMove), parse_input(&game, "e5"));
+
+        game.make_move(e4).unwrap();
+        assert_eq!(
+            Ok(Input::Move(Move::arabic("d7d5").unwrap())),
+            parse_input(&game, "d5")
+        );
     }
 }
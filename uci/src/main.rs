@@ -0,0 +1,281 @@
+//! A front-end speaking the Universal Chess Interface protocol on stdin and
+//! stdout, so that the engine can be used from chess GUIs and match runners.
+
+use chess_engine::{
+    tt, Board, Color, Error, Game, Limits, Move, Score, SearchResult, Searcher, TranspositionTable,
+};
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Time kept in reserve when playing on the clock, to make up for the time it
+/// takes to report the move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// Parameters given to the `go` command.
+#[derive(Debug, Default)]
+struct GoParams {
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    movetime: Option<Duration>,
+    wtime: Option<Duration>,
+    btime: Option<Duration>,
+    winc: Option<Duration>,
+    binc: Option<Duration>,
+    movestogo: Option<u32>,
+    infinite: bool,
+}
+
+impl GoParams {
+    fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> Self {
+        let mut params = Self::default();
+        let millis =
+            |arg: Option<&str>| arg.and_then(|a| a.parse().ok()).map(Duration::from_millis);
+        while let Some(arg) = args.next() {
+            match arg {
+                "depth" => params.depth = args.next().and_then(|a| a.parse().ok()),
+                "nodes" => params.nodes = args.next().and_then(|a| a.parse().ok()),
+                "movetime" => params.movetime = millis(args.next()),
+                "wtime" => params.wtime = millis(args.next()),
+                "btime" => params.btime = millis(args.next()),
+                "winc" => params.winc = millis(args.next()),
+                "binc" => params.binc = millis(args.next()),
+                "movestogo" => params.movestogo = args.next().and_then(|a| a.parse().ok()),
+                "infinite" => params.infinite = true,
+                _ => {}
+            }
+        }
+        params
+    }
+    /// Converts the parameters to search limits for `color` to move.
+    fn limits(&self, color: Color) -> Limits {
+        let mut limits = Limits {
+            depth: self.depth,
+            nodes: self.nodes,
+            time: self.movetime,
+        };
+        if self.infinite {
+            return Limits::default();
+        }
+        let (time, inc) = match color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        if let (None, Some(time)) = (limits.time, time) {
+            // Spread the remaining time over the moves left, assuming there
+            // are always some 30 moves left when playing sudden death
+            let moves_left = self.movestogo.unwrap_or(30).max(1);
+            let budget = time / moves_left + inc.unwrap_or_default() / 2;
+            limits.time = Some(budget.min(time.saturating_sub(MOVE_OVERHEAD)));
+        }
+        limits
+    }
+}
+
+/// Largest transposition table allowed through the "Hash" option, in
+/// megabytes
+const MAX_HASH: usize = 65536;
+
+/// A search running in a background thread, which hands back the
+/// transposition table when done.
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<TranspositionTable>,
+}
+
+struct Uci {
+    game: Game,
+    /// Kept between searches, except while one is running
+    table: Option<TranspositionTable>,
+    search: Option<Search>,
+    /// Set through the "UCI_Chess960" option, in which case castling moves
+    /// are written as the king capturing its own rook
+    chess960: bool,
+}
+
+impl Uci {
+    fn new() -> Self {
+        Self {
+            game: Game::new(Board::default()),
+            table: None,
+            search: None,
+            chess960: false,
+        }
+    }
+    /// Handles one line of input. Returns `false` when the engine should quit.
+    fn handle(&mut self, line: &str) -> bool {
+        let mut args = line.split_ascii_whitespace();
+        match args.next() {
+            Some("uci") => {
+                println!("id name mathm-chess");
+                println!("id author mathiasmagnusson");
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    tt::DEFAULT_MEGABYTES,
+                    MAX_HASH
+                );
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                self.stop();
+                self.game = Game::new(Board::default());
+                if let Some(table) = &mut self.table {
+                    table.clear();
+                }
+            }
+            Some("position") => {
+                self.stop();
+                if let Err(err) = self.position(args) {
+                    println!("info string {}", err);
+                }
+            }
+            Some("go") => self.go(GoParams::parse(args)),
+            Some("stop") => self.stop(),
+            Some("setoption") => self.set_option(args),
+            Some("quit") => {
+                self.stop();
+                return false;
+            }
+            Some(cmd) => println!("info string unknown command {}", cmd),
+            None => {}
+        }
+        true
+    }
+    /// Handles `position [startpos | fen <fen>] [moves <move>...]`
+    fn position<'a>(&mut self, args: impl Iterator<Item = &'a str>) -> Result<(), Error> {
+        let args = args.collect::<Vec<_>>();
+        let moves_start = args.iter().position(|&a| a == "moves");
+        let (setup, moves) = args.split_at(moves_start.unwrap_or(args.len()));
+        let mut board = match setup.split_first() {
+            Some((&"startpos", _)) => Board::default(),
+            Some((&"fen", fen)) => Board::from_fen(&fen.join(" "))?,
+            _ => return Err(Error::ParsingError),
+        };
+        if self.chess960 {
+            board.set_chess960(true);
+        }
+        let mut game = Game::new(board);
+        for m in moves.iter().skip(1) {
+            game.make_move(Move::arabic(m)?)?;
+        }
+        self.game = game;
+        Ok(())
+    }
+    /// Handles `setoption name <id> [value <x>]`.
+    fn set_option<'a>(&mut self, args: impl Iterator<Item = &'a str>) {
+        let args = args.collect::<Vec<_>>();
+        let value_start = args.iter().position(|&a| a == "value");
+        let (name, value) = args.split_at(value_start.unwrap_or(args.len()));
+        let name = name
+            .iter()
+            .skip_while(|&&a| a == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = value.iter().skip(1).copied().collect::<Vec<_>>().join(" ");
+        match name.as_str() {
+            "Hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH).contains(&mb) => {
+                    self.stop();
+                    self.table = Some(TranspositionTable::new(mb));
+                }
+                _ => println!("info string invalid Hash value {}", value),
+            },
+            "UCI_Chess960" => match value.parse::<bool>() {
+                Ok(chess960) => self.chess960 = chess960,
+                Err(_) => println!("info string invalid UCI_Chess960 value {}", value),
+            },
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+    /// Starts searching the current position in the background. The best
+    /// move is reported when the search is done or stopped, but in infinite
+    /// mode not before "stop" or "quit" arrives, as the protocol requires.
+    fn go(&mut self, params: GoParams) {
+        self.stop();
+        let limits = params.limits(self.game.board().next_to_move());
+        let table = self.table.take().unwrap_or_default();
+        let searcher = Searcher::with_table(limits, table);
+        let stop = searcher.stop_flag();
+        let stopped = stop.clone();
+        let infinite = params.infinite;
+        let game = self.game.clone();
+        let handle = thread::spawn(move || {
+            let mut searcher = searcher;
+            let board = game.board();
+            let result = searcher.search_with(&game, |result| print_info(board, result));
+            // The search may end by itself, e.g. on finding a mate
+            while infinite && !stopped.load(Ordering::Relaxed) {
+                thread::park();
+            }
+            match result.best_move {
+                Some(m) => println!("bestmove {}", format_moves(board, &[m])),
+                None => println!("bestmove 0000"),
+            }
+            searcher.into_table()
+        });
+        self.search = Some(Search { stop, handle });
+    }
+    /// Stops the running search, if any, and waits for it to report its move.
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            search.handle.thread().unpark();
+            self.table = Some(search.handle.join().expect("search thread panicked"));
+        }
+    }
+}
+
+/// Writes `moves`, made one after the other from `board`, with castling
+/// written the way the GUI expects it.
+fn format_moves(board: &Board, moves: &[Move]) -> String {
+    let mut board = board.clone();
+    moves
+        .iter()
+        .map(|&m| {
+            let written = board.display_castling(m).as_arabic();
+            board.make_move_unchecked(m);
+            written
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn print_info(board: &Board, result: &SearchResult) {
+    let score = match result.score {
+        Score::Centipawns(cp) => format!("cp {}", cp),
+        Score::Mate(moves) => format!("mate {}", moves),
+    };
+    let millis = result.time.as_millis();
+    let nps = (result.nodes as u128 * 1000)
+        .checked_div(millis)
+        .unwrap_or(0);
+    let pv = format_moves(board, &result.pv);
+    println!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth, score, result.nodes, nps, millis, pv
+    );
+}
+
+fn main() {
+    let mut uci = Uci::new();
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !uci.handle(&line) {
+            return;
+        }
+    }
+    // Treat the end of input like `quit`
+    uci.stop();
+}